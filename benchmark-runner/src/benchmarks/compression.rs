@@ -0,0 +1,238 @@
+use std::{
+    borrow::Cow,
+    fs,
+    io::{Read, Write},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use log::info;
+use reqwest::{
+    Client, Response, StatusCode,
+    header::{ACCEPT_ENCODING, CONTENT_ENCODING},
+};
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::{
+    Benchmark, BenchmarkError, BenchmarkOkResult, BenchmarkResult, RunOptions,
+    benchmarks::json_echo::{JsonDocumentShape, generate_document},
+    hyper_engine::RequestTemplate,
+    run_requests,
+};
+
+/// Compressible document served by the compression route, generated by the runner.
+pub const DOCUMENT_PATH: &str = "/assets/compressible.json";
+
+const DOCUMENT_SHAPE: JsonDocumentShape = JsonDocumentShape {
+    size_bytes: 64 * 1024,
+    depth: 4,
+};
+
+/// Content coding requested with `Accept-Encoding`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ContentCoding {
+    /// Uncompressed, the baseline the other codings are compared with.
+    Identity,
+    Gzip,
+    Brotli,
+    Zstd,
+}
+
+impl ContentCoding {
+    pub const COMPRESSED: [ContentCoding; 3] = [
+        ContentCoding::Gzip,
+        ContentCoding::Brotli,
+        ContentCoding::Zstd,
+    ];
+
+    /// Token used in `Accept-Encoding` and `Content-Encoding`.
+    pub fn token(&self) -> &'static str {
+        match self {
+            ContentCoding::Identity => "identity",
+            ContentCoding::Gzip => "gzip",
+            ContentCoding::Brotli => "br",
+            ContentCoding::Zstd => "zstd",
+        }
+    }
+
+    pub fn encode(&self, content: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+        match self {
+            ContentCoding::Identity => Ok(content.to_vec()),
+            ContentCoding::Gzip => {
+                let mut encoder = GzEncoder::new(vec![], Compression::default());
+                encoder.write_all(content)?;
+                encoder.finish()
+            }
+            ContentCoding::Brotli => {
+                let mut encoded = vec![];
+                let mut encoder = brotli::CompressorWriter::new(&mut encoded, 4096, 4, 22);
+                encoder.write_all(content)?;
+                drop(encoder);
+                Ok(encoded)
+            }
+            ContentCoding::Zstd => zstd::encode_all(content, 3),
+        }
+    }
+
+    fn decode(&self, body: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+        let mut decoded = vec![];
+        match self {
+            ContentCoding::Identity => decoded.extend_from_slice(body),
+            ContentCoding::Gzip => {
+                GzDecoder::new(body).read_to_end(&mut decoded)?;
+            }
+            ContentCoding::Brotli => {
+                brotli::Decompressor::new(body, 4096).read_to_end(&mut decoded)?;
+            }
+            ContentCoding::Zstd => decoded = zstd::decode_all(body)?,
+        }
+        Ok(decoded)
+    }
+}
+
+/// Response body sizes of a compression run, summed over its valid responses.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CompressionStats {
+    pub responses: u64,
+    /// Bytes as sent by the webserver, before decoding.
+    pub wire_bytes: u64,
+    /// Bytes after decoding.
+    pub content_bytes: u64,
+}
+
+impl CompressionStats {
+    pub fn merge(&self, other: &CompressionStats) -> CompressionStats {
+        CompressionStats {
+            responses: self.responses + other.responses,
+            wire_bytes: self.wire_bytes + other.wire_bytes,
+            content_bytes: self.content_bytes + other.content_bytes,
+        }
+    }
+}
+
+struct CompressionBenchmark {
+    url: String,
+    template: RequestTemplate,
+    coding: ContentCoding,
+    document: Bytes,
+    responses: AtomicU64,
+    wire_bytes: AtomicU64,
+}
+
+#[async_trait]
+impl Benchmark for CompressionBenchmark {
+    async fn make_request(
+        &self,
+        client: Client,
+        _iteration: usize,
+    ) -> Result<Response, BenchmarkError> {
+        let response = client
+            .get(&self.url)
+            .header(ACCEPT_ENCODING, self.coding.token())
+            .send()
+            .await?;
+        Ok(response)
+    }
+
+    async fn check_response(
+        &self,
+        _iteration: usize,
+        start: Instant,
+        response: Response,
+    ) -> Result<BenchmarkResult, BenchmarkError> {
+        if response.status() != StatusCode::OK {
+            return Ok(BenchmarkResult::InvalidStatusCode(
+                response.status().as_u16(),
+            ));
+        }
+        let content_encoding = response
+            .headers()
+            .get(CONTENT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("identity")
+            .to_string();
+        if content_encoding != self.coding.token() {
+            return Ok(BenchmarkResult::InvalidResponse(format!(
+                "Expected content encoding \"{}\" found \"{content_encoding}\"",
+                self.coding.token()
+            )));
+        }
+
+        let body = response.bytes().await?;
+        let content = match self.coding.decode(&body) {
+            Ok(content) => content,
+            Err(err) => {
+                return Ok(BenchmarkResult::InvalidResponse(format!(
+                    "Invalid {content_encoding} body: {err}"
+                )));
+            }
+        };
+        if content != self.document {
+            return Ok(BenchmarkResult::InvalidResponse(format!(
+                "Decoded body differs from the document, {} bytes found {} bytes expected",
+                content.len(),
+                self.document.len()
+            )));
+        }
+        self.responses.fetch_add(1, Ordering::Relaxed);
+        self.wire_bytes
+            .fetch_add(body.len() as u64, Ordering::Relaxed);
+
+        Ok(BenchmarkResult::Ok(Box::new(BenchmarkOkResult {
+            time: start.elapsed(),
+            iterations: 1,
+            ..Default::default()
+        })))
+    }
+
+    fn request_template(
+        &self,
+        _iteration: usize,
+    ) -> Result<Cow<'_, RequestTemplate>, BenchmarkError> {
+        Ok(Cow::Borrowed(&self.template))
+    }
+}
+
+pub async fn benchmark_compression(
+    options: RunOptions,
+    coding: ContentCoding,
+) -> Result<BenchmarkResult, BenchmarkError> {
+    info!("benchmark compression {coding:?} {options:?}");
+
+    let document = Bytes::from(fs::read(DOCUMENT_PATH)?);
+    let url = options.url("/benchmark/compressed");
+    let benchmark = Arc::new(CompressionBenchmark {
+        template: RequestTemplate::get(&url)?.with_header(ACCEPT_ENCODING, coding.token())?,
+        url,
+        coding,
+        document,
+        responses: AtomicU64::new(0),
+        wire_bytes: AtomicU64::new(0),
+    });
+
+    let mut result = run_requests(options, benchmark.clone()).await?;
+    if let BenchmarkResult::Ok(result) = &mut result {
+        let responses = benchmark.responses.load(Ordering::Relaxed);
+        result.compression = Some(CompressionStats {
+            responses,
+            wire_bytes: benchmark.wire_bytes.load(Ordering::Relaxed),
+            content_bytes: responses * benchmark.document.len() as u64,
+        });
+    }
+    Ok(result)
+}
+
+/// Writes the document served by the compression route into the assets volume.
+pub fn generate_compressible_document() -> Result<(), BenchmarkError> {
+    let document = generate_document(0, DOCUMENT_SHAPE);
+    fs::write(DOCUMENT_PATH, serde_json::to_vec_pretty(&document)?)?;
+    Ok(())
+}
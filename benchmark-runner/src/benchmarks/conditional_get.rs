@@ -0,0 +1,186 @@
+use std::{borrow::Cow, fs, sync::Arc};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use log::{info, warn};
+use reqwest::{
+    Client, Response, StatusCode,
+    header::{ETAG, HeaderName, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+};
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::{
+    Benchmark, BenchmarkError, BenchmarkOkResult, BenchmarkResult, RunOptions,
+    connection::ClientPool, hyper_engine::RequestTemplate, run_requests,
+};
+
+/// Entity tag no webserver generates, sent to take the miss path.
+const STALE_ETAG: &str = "\"webserver-benchmarks-stale\"";
+
+/// Date before any file was modified, sent to take the miss path.
+const STALE_DATE: &str = "Thu, 01 Jan 1970 00:00:01 GMT";
+
+/// Which validator the conditional GET benchmark sends and whether it matches.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct ConditionalGetOptions {
+    pub validator: CacheValidator,
+    /// Send the validator the webserver returned, expecting `304 Not Modified`, rather than a
+    /// stale one, expecting the full body.
+    pub hit: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum CacheValidator {
+    /// `If-None-Match` with the `ETag` response header.
+    ETag,
+    /// `If-Modified-Since` with the `Last-Modified` response header.
+    LastModified,
+}
+
+impl CacheValidator {
+    fn response_header(&self) -> HeaderName {
+        match self {
+            CacheValidator::ETag => ETAG,
+            CacheValidator::LastModified => LAST_MODIFIED,
+        }
+    }
+
+    fn request_header(&self) -> HeaderName {
+        match self {
+            CacheValidator::ETag => IF_NONE_MATCH,
+            CacheValidator::LastModified => IF_MODIFIED_SINCE,
+        }
+    }
+
+    fn stale_value(&self) -> &'static str {
+        match self {
+            CacheValidator::ETag => STALE_ETAG,
+            CacheValidator::LastModified => STALE_DATE,
+        }
+    }
+}
+
+struct ConditionalGetBenchmark {
+    url: String,
+    template: RequestTemplate,
+    header: HeaderName,
+    value: String,
+    hit: bool,
+    binary_data: Bytes,
+}
+
+#[async_trait]
+impl Benchmark for ConditionalGetBenchmark {
+    async fn make_request(
+        &self,
+        client: Client,
+        _iteration: usize,
+    ) -> Result<Response, BenchmarkError> {
+        let response = client
+            .get(&self.url)
+            .header(&self.header, &self.value)
+            .send()
+            .await?;
+        Ok(response)
+    }
+
+    async fn check_response(
+        &self,
+        _iteration: usize,
+        start: Instant,
+        response: Response,
+    ) -> Result<BenchmarkResult, BenchmarkError> {
+        let expected_status = if self.hit {
+            StatusCode::NOT_MODIFIED
+        } else {
+            StatusCode::OK
+        };
+        if response.status() != expected_status {
+            return Ok(BenchmarkResult::InvalidStatusCode(
+                response.status().as_u16(),
+            ));
+        }
+
+        let bytes = response.bytes().await?;
+        if self.hit && !bytes.is_empty() {
+            return Ok(BenchmarkResult::InvalidResponse(format!(
+                "Expected empty body with 304 found {} bytes",
+                bytes.len()
+            )));
+        }
+        if !self.hit && bytes != self.binary_data {
+            return Ok(BenchmarkResult::InvalidResponse(
+                "Bytes data mismatch".to_string(),
+            ));
+        }
+
+        Ok(BenchmarkResult::Ok(Box::new(BenchmarkOkResult {
+            time: start.elapsed(),
+            iterations: 1,
+            ..Default::default()
+        })))
+    }
+
+    fn request_template(
+        &self,
+        _iteration: usize,
+    ) -> Result<Cow<'_, RequestTemplate>, BenchmarkError> {
+        Ok(Cow::Borrowed(&self.template))
+    }
+}
+
+/// Requests the download binary route with a cache validator. The validator is taken from an
+/// unconditional request made first, and webservers that don't send it are flagged with an
+/// invalid response instead of being benchmarked.
+pub async fn benchmark_conditional_get(
+    options: RunOptions,
+    conditional_options: ConditionalGetOptions,
+) -> Result<BenchmarkResult, BenchmarkError> {
+    info!("benchmark conditional get {conditional_options:?} {options:?}");
+
+    let binary_data = Bytes::from(fs::read("/assets/download-binary.png")?);
+    let url = options.url("/benchmark/download-binary");
+    let validator = conditional_options.validator;
+
+    let (client, _permit) = ClientPool::new(&options)?.acquire(0).await?;
+    let response = client.get(&url).send().await?;
+    if response.status() != StatusCode::OK {
+        return Ok(BenchmarkResult::InvalidStatusCode(
+            response.status().as_u16(),
+        ));
+    }
+    let Some(current) = response
+        .headers()
+        .get(validator.response_header())
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+    else {
+        warn!(
+            "{url} has no {} header, caching is not supported",
+            validator.response_header()
+        );
+        return Ok(BenchmarkResult::InvalidResponse(format!(
+            "Missing {} header",
+            validator.response_header()
+        )));
+    };
+
+    let value = if conditional_options.hit {
+        current
+    } else {
+        validator.stale_value().to_string()
+    };
+    let header = validator.request_header();
+    let benchmark = ConditionalGetBenchmark {
+        template: RequestTemplate::get(&url)?.with_header(header.clone(), &value)?,
+        url,
+        header,
+        value,
+        hit: conditional_options.hit,
+        binary_data,
+    };
+    run_requests(options, Arc::new(benchmark)).await
+}
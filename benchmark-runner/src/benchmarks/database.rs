@@ -0,0 +1,402 @@
+use std::{
+    borrow::Cow,
+    fs,
+    path::Path,
+    sync::{Arc, LazyLock},
+};
+
+use async_trait::async_trait;
+use log::info;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use reqwest::{Client, Response, StatusCode, header::CONTENT_TYPE};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::{
+    Benchmark, BenchmarkError, BenchmarkOkResult, BenchmarkResult, RunOptions,
+    hyper_engine::RequestTemplate, run_requests,
+};
+
+pub const SQLITE_PATH: &str = "/assets/db/benchmark.sqlite";
+
+/// Rows of the `world` table, with ids `1..=WORLD_ROWS` and random numbers in the same range.
+pub const WORLD_ROWS: i32 = 10000;
+
+/// Most rows a multiple query or update request may ask for, larger counts are clamped.
+pub const MAX_QUERIES: usize = 500;
+
+const ADDITIONAL_FORTUNE: &str = "Additional fortune added at request time.";
+
+/// Rows of the `fortune` table, the same as TechEmpower's so that escaping and sorting of non-ASCII
+/// text is exercised.
+const FORTUNES: [&str; 12] = [
+    "fortune: No such file or directory",
+    "A computer scientist is someone who fixes things that aren't broken.",
+    "After enough decimal places, nobody gives a damn.",
+    "A bad random number generator: 1, 1, 1, 1, 1, 4.33e+67, 1, 1, 1",
+    "A computer program does what you tell it to do, not what you want it to do.",
+    "Emacs is a nice operating system, but I prefer UNIX. — Tom Christaensen",
+    "Any program that runs right is obsolete.",
+    "A list is only as strong as its weakest link. — Donald Knuth",
+    "Feature: A bug with seniority.",
+    "Computers make very fast, very accurate mistakes.",
+    "<script>alert(\"This should not be displayed in a browser alert box.\");</script>",
+    "フレームワークのベンチマーク",
+];
+
+/// Contents of the `world` table as generated, indexed by id - 1.
+static WORLDS: LazyLock<Vec<World>> = LazyLock::new(|| {
+    let mut rng = StdRng::seed_from_u64(0);
+    (1..=WORLD_ROWS)
+        .map(|id| World {
+            id,
+            random_number: rng.random_range(1..=WORLD_ROWS),
+        })
+        .collect()
+});
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct World {
+    pub id: i32,
+    pub random_number: i32,
+}
+
+/// Database a webserver queries, each served under its own route prefix.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum DatabaseBackend {
+    /// The SQLite file generated into the assets volume, at `/benchmark/sqlite/*`.
+    Sqlite,
+    /// The PostgreSQL sidecar, at `/benchmark/db/*`.
+    Postgres,
+}
+
+impl DatabaseBackend {
+    fn route_prefix(&self) -> &'static str {
+        match self {
+            DatabaseBackend::Sqlite => "/benchmark/sqlite",
+            DatabaseBackend::Postgres => "/benchmark/db",
+        }
+    }
+}
+
+/// Workloads modelled on TechEmpower's database tests.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "type"
+)]
+pub enum DatabaseQuery {
+    /// One random `world` row.
+    Single,
+    /// `queries` random `world` rows, each read with its own query.
+    Multiple { queries: usize },
+    /// `queries` random `world` rows, each given a new random number and written back.
+    Updates { queries: usize },
+    /// Every `fortune` row plus one added at request time, sorted by message and rendered as an
+    /// HTML table.
+    Fortunes,
+}
+
+impl DatabaseQuery {
+    fn path(&self) -> String {
+        match self {
+            DatabaseQuery::Single => "/single".to_string(),
+            DatabaseQuery::Multiple { queries } => format!("/queries?queries={queries}"),
+            DatabaseQuery::Updates { queries } => format!("/updates?queries={queries}"),
+            DatabaseQuery::Fortunes => "/fortunes".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseOptions {
+    pub backend: DatabaseBackend,
+    pub query: DatabaseQuery,
+}
+
+struct DatabaseBenchmark {
+    url: String,
+    template: RequestTemplate,
+    query: DatabaseQuery,
+    fortunes_html: String,
+}
+
+#[async_trait]
+impl Benchmark for DatabaseBenchmark {
+    async fn make_request(
+        &self,
+        client: Client,
+        _iteration: usize,
+    ) -> Result<Response, BenchmarkError> {
+        let response = client.get(&self.url).send().await?;
+        Ok(response)
+    }
+
+    async fn check_response(
+        &self,
+        _iteration: usize,
+        start: Instant,
+        response: Response,
+    ) -> Result<BenchmarkResult, BenchmarkError> {
+        if response.status() != StatusCode::OK {
+            return Ok(BenchmarkResult::InvalidStatusCode(
+                response.status().as_u16(),
+            ));
+        }
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let body = response.bytes().await?;
+
+        let error = match self.query {
+            DatabaseQuery::Single => match serde_json::from_slice::<World>(&body) {
+                Ok(world) => check_world(&world),
+                Err(err) => Some(format!("Invalid JSON: {err}")),
+            },
+            DatabaseQuery::Multiple { queries } => {
+                match serde_json::from_slice::<Vec<World>>(&body) {
+                    Ok(worlds) => check_count(&worlds, queries)
+                        .or_else(|| worlds.iter().find_map(check_world)),
+                    Err(err) => Some(format!("Invalid JSON: {err}")),
+                }
+            }
+            DatabaseQuery::Updates { queries } => match serde_json::from_slice::<Vec<World>>(&body)
+            {
+                // rows are updated concurrently, so only the ranges can be checked
+                Ok(worlds) => check_count(&worlds, queries)
+                    .or_else(|| worlds.iter().find_map(check_world_range)),
+                Err(err) => Some(format!("Invalid JSON: {err}")),
+            },
+            DatabaseQuery::Fortunes => {
+                if !content_type.starts_with("text/html") {
+                    Some(format!(
+                        "Expected content type \"text/html\" found \"{content_type}\""
+                    ))
+                } else if normalize_html(&String::from_utf8_lossy(&body)) != self.fortunes_html {
+                    Some("Fortunes table differs from the expected one".to_string())
+                } else {
+                    None
+                }
+            }
+        };
+        if let Some(error) = error {
+            return Ok(BenchmarkResult::InvalidResponse(error));
+        }
+
+        Ok(BenchmarkResult::Ok(Box::new(BenchmarkOkResult {
+            time: start.elapsed(),
+            iterations: 1,
+            ..Default::default()
+        })))
+    }
+
+    fn request_template(
+        &self,
+        _iteration: usize,
+    ) -> Result<Cow<'_, RequestTemplate>, BenchmarkError> {
+        Ok(Cow::Borrowed(&self.template))
+    }
+}
+
+fn check_count(worlds: &[World], queries: usize) -> Option<String> {
+    let expected = queries.clamp(1, MAX_QUERIES);
+    (worlds.len() != expected).then(|| format!("Expected {expected} rows found {}", worlds.len()))
+}
+
+fn check_world_range(world: &World) -> Option<String> {
+    let range = 1..=WORLD_ROWS;
+    (!range.contains(&world.id) || !range.contains(&world.random_number))
+        .then(|| format!("Row out of range: {world:?}"))
+}
+
+fn check_world(world: &World) -> Option<String> {
+    if let Some(error) = check_world_range(world) {
+        return Some(error);
+    }
+    let expected = &WORLDS[(world.id - 1) as usize];
+    (world != expected).then(|| format!("Expected {expected:?} found {world:?}"))
+}
+
+pub async fn benchmark_database(
+    options: RunOptions,
+    database_options: DatabaseOptions,
+) -> Result<BenchmarkResult, BenchmarkError> {
+    info!("benchmark database {database_options:?} {options:?}");
+    let url = options.url(&format!(
+        "{}{}",
+        database_options.backend.route_prefix(),
+        database_options.query.path()
+    ));
+    let benchmark = DatabaseBenchmark {
+        template: RequestTemplate::get(&url)?,
+        url,
+        query: database_options.query,
+        fortunes_html: normalize_html(&fortunes_html()),
+    };
+    run_requests(options, Arc::new(benchmark)).await
+}
+
+/// Writes a fresh SQLite database with the `world` and `fortune` tables. Updates change the
+/// `world` table, so it is recreated before every webserver runs.
+pub fn generate_sqlite_database() -> Result<(), BenchmarkError> {
+    info!("generating SQLite database {SQLITE_PATH}");
+    if let Some(dir) = Path::new(SQLITE_PATH).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = fs::remove_file(format!("{SQLITE_PATH}{suffix}"));
+    }
+
+    let mut connection = Connection::open(SQLITE_PATH)?;
+    // readers don't block the writer in WAL mode, which persists in the file
+    connection.pragma_update(None, "journal_mode", "WAL")?;
+    connection.execute_batch(
+        "CREATE TABLE world (id INTEGER PRIMARY KEY, random_number INTEGER NOT NULL);
+         CREATE TABLE fortune (id INTEGER PRIMARY KEY, message TEXT NOT NULL);",
+    )?;
+    let transaction = connection.transaction()?;
+    {
+        let mut insert =
+            transaction.prepare("INSERT INTO world (id, random_number) VALUES (?1, ?2)")?;
+        for world in WORLDS.iter() {
+            insert.execute((world.id, world.random_number))?;
+        }
+        let mut insert =
+            transaction.prepare("INSERT INTO fortune (id, message) VALUES (?1, ?2)")?;
+        for (index, message) in FORTUNES.iter().enumerate() {
+            insert.execute((index as i32 + 1, message))?;
+        }
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+/// SQL creating and filling the same tables in the PostgreSQL sidecar.
+pub fn postgres_seed_sql() -> String {
+    let mut sql = String::from(
+        "CREATE TABLE world (id integer PRIMARY KEY, random_number integer NOT NULL);\n\
+         CREATE TABLE fortune (id integer PRIMARY KEY, message text NOT NULL);\n\
+         INSERT INTO world (id, random_number) VALUES\n",
+    );
+    let worlds: Vec<String> = WORLDS
+        .iter()
+        .map(|world| format!("({}, {})", world.id, world.random_number))
+        .collect();
+    sql.push_str(&worlds.join(",\n"));
+    sql.push_str(";\nINSERT INTO fortune (id, message) VALUES\n");
+    let fortunes: Vec<String> = FORTUNES
+        .iter()
+        .enumerate()
+        .map(|(index, message)| format!("({}, '{}')", index + 1, message.replace('\'', "''")))
+        .collect();
+    sql.push_str(&fortunes.join(",\n"));
+    sql.push_str(";\n");
+    sql
+}
+
+/// The fortunes page a correct webserver returns.
+pub fn fortunes_html() -> String {
+    let mut fortunes: Vec<(usize, &str)> = FORTUNES
+        .iter()
+        .enumerate()
+        .map(|(index, message)| (index + 1, *message))
+        .collect();
+    fortunes.push((0, ADDITIONAL_FORTUNE));
+    fortunes.sort_by(|a, b| a.1.cmp(b.1));
+
+    let mut html = String::from(
+        "<!DOCTYPE html><html><head><title>Fortunes</title></head><body><table>\
+         <tr><th>id</th><th>message</th></tr>",
+    );
+    for (id, message) in fortunes {
+        html.push_str(&format!(
+            "<tr><td>{id}</td><td>{}</td></tr>",
+            escape_html(message)
+        ));
+    }
+    html.push_str("</table></body></html>");
+    html
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Drops whitespace between tags and settles on one spelling of the escaped quotes, so that
+/// template engines that indent their output or escape differently still compare equal.
+fn normalize_html(html: &str) -> String {
+    let html = html
+        .replace("&#x27;", "&#39;")
+        .replace("&#x22;", "&quot;")
+        .replace("&#34;", "&quot;");
+    let mut normalized = String::with_capacity(html.len());
+    for segment in html.split('<') {
+        if normalized.is_empty() && segment.trim().is_empty() {
+            continue;
+        }
+        match segment.split_once('>') {
+            Some((tag, text)) => {
+                normalized.push('<');
+                normalized.push_str(tag);
+                normalized.push('>');
+                if !text.trim().is_empty() {
+                    normalized.push_str(text);
+                }
+            }
+            None => normalized.push_str(segment),
+        }
+    }
+    normalized
+}
+
+/// Content type and body a correct webserver returns for `route` below a backend's prefix, used
+/// by the calibration null server, which answers from the generated rows instead of a database.
+pub fn reference_response(route: &str, query: Option<&str>) -> Option<(&'static str, Vec<u8>)> {
+    let queries = || {
+        query
+            .and_then(|query| query.strip_prefix("queries="))
+            .and_then(|queries| queries.parse::<usize>().ok())
+            .unwrap_or(1)
+            .clamp(1, MAX_QUERIES)
+    };
+    let mut rng = rand::rng();
+    let mut random_world = || WORLDS[rng.random_range(0..WORLDS.len())];
+
+    let body = match route {
+        "/single" => serde_json::to_vec(&random_world()),
+        "/queries" => {
+            serde_json::to_vec(&(0..queries()).map(|_| random_world()).collect::<Vec<_>>())
+        }
+        "/updates" => {
+            let worlds: Vec<World> = (0..queries())
+                .map(|_| World {
+                    random_number: rand::rng().random_range(1..=WORLD_ROWS),
+                    ..random_world()
+                })
+                .collect();
+            serde_json::to_vec(&worlds)
+        }
+        "/fortunes" => return Some(("text/html; charset=utf-8", fortunes_html().into_bytes())),
+        _ => return None,
+    };
+    body.ok().map(|body| ("application/json", body))
+}
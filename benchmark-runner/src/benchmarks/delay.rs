@@ -0,0 +1,124 @@
+use std::{borrow::Cow, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use log::info;
+use reqwest::{Client, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::{
+    Benchmark, BenchmarkError, BenchmarkOkResult, BenchmarkResult, RunOptions,
+    hyper_engine::RequestTemplate, run_requests, time_series::TimeSeriesBucket,
+};
+
+/// How far above the delay a second's p99 latency may rise for its concurrency to count as
+/// sustained.
+const LATENCY_BUDGET: f64 = 1.5;
+
+/// How long the webserver waits before replying.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct DelayOptions {
+    pub delay_ms: u64,
+}
+
+struct DelayBenchmark {
+    url: String,
+    template: RequestTemplate,
+    delay: Duration,
+    expected: String,
+}
+
+#[async_trait]
+impl Benchmark for DelayBenchmark {
+    async fn make_request(
+        &self,
+        client: Client,
+        _iteration: usize,
+    ) -> Result<Response, BenchmarkError> {
+        let response = client.get(&self.url).send().await?;
+        Ok(response)
+    }
+
+    async fn check_response(
+        &self,
+        _iteration: usize,
+        start: Instant,
+        response: Response,
+    ) -> Result<BenchmarkResult, BenchmarkError> {
+        if response.status() != StatusCode::OK {
+            return Ok(BenchmarkResult::InvalidStatusCode(
+                response.status().as_u16(),
+            ));
+        }
+        let text = response.text().await?;
+        if text != self.expected {
+            return Ok(BenchmarkResult::InvalidResponse(format!(
+                "Expected \"{}\" found \"{text}\"",
+                self.expected
+            )));
+        }
+        let time = start.elapsed();
+        if time < self.delay {
+            return Ok(BenchmarkResult::InvalidResponse(format!(
+                "Replied after {}ms, before the {}ms delay",
+                time.as_millis(),
+                self.delay.as_millis()
+            )));
+        }
+
+        Ok(BenchmarkResult::Ok(Box::new(BenchmarkOkResult {
+            time,
+            iterations: 1,
+            ..Default::default()
+        })))
+    }
+
+    fn request_template(
+        &self,
+        _iteration: usize,
+    ) -> Result<Cow<'_, RequestTemplate>, BenchmarkError> {
+        Ok(Cow::Borrowed(&self.template))
+    }
+}
+
+pub async fn benchmark_delay(
+    options: RunOptions,
+    delay_options: DelayOptions,
+) -> Result<BenchmarkResult, BenchmarkError> {
+    info!("benchmark delay {delay_options:?} {options:?}");
+    let url = options.url(&format!("/benchmark/delay?ms={}", delay_options.delay_ms));
+    let benchmark = DelayBenchmark {
+        template: RequestTemplate::get(&url)?,
+        url,
+        delay: Duration::from_millis(delay_options.delay_ms),
+        expected: reference_response(delay_options.delay_ms),
+    };
+
+    // a burst completes within a fraction of a second, so per-second rates say nothing about it
+    let is_burst = options.load_profile.is_burst();
+    let mut result = run_requests(options, Arc::new(benchmark)).await?;
+    if let (BenchmarkResult::Ok(result), false) = (&mut result, is_burst) {
+        result.sustained_concurrency = sustained_concurrency(&result.time_series, delay_options);
+    }
+    Ok(result)
+}
+
+/// The response body a correct webserver returns after waiting `delay_ms`.
+pub fn reference_response(delay_ms: u64) -> String {
+    delay_ms.to_string()
+}
+
+/// Most requests waiting at once, by Little's law the completion rate times the mean latency,
+/// in any second without errors whose p99 latency stayed within [`LATENCY_BUDGET`] of the delay.
+pub fn sustained_concurrency(
+    time_series: &[TimeSeriesBucket],
+    delay_options: DelayOptions,
+) -> Option<f64> {
+    let budget_ms = delay_options.delay_ms as f64 * LATENCY_BUDGET;
+    time_series
+        .iter()
+        .filter(|bucket| bucket.errors == 0 && bucket.latency_p99_ms <= budget_ms)
+        .map(|bucket| bucket.rps as f64 * bucket.latency_mean_ms / 1000.0)
+        .max_by(f64::total_cmp)
+}
@@ -1,4 +1,5 @@
 use std::{
+    borrow::Cow,
     fs::{self},
     sync::Arc,
 };
@@ -9,9 +10,14 @@ use log::info;
 use reqwest::{Client, Response, StatusCode};
 use tokio::time::Instant;
 
-use crate::{Benchmark, BenchmarkError, BenchmarkOkResult, BenchmarkResult, run_requests};
+use crate::{
+    Benchmark, BenchmarkError, BenchmarkOkResult, BenchmarkResult, RunOptions,
+    hyper_engine::RequestTemplate, run_requests,
+};
 
 struct DownloadBinaryBenchmark {
+    url: String,
+    template: RequestTemplate,
     binary_data: Bytes,
 }
 
@@ -22,10 +28,7 @@ impl Benchmark for DownloadBinaryBenchmark {
         client: Client,
         _iteration: usize,
     ) -> Result<Response, BenchmarkError> {
-        let response = client
-            .get("http://web:8000/benchmark/download-binary")
-            .send()
-            .await?;
+        let response = client.get(&self.url).send().await?;
         Ok(response)
     }
 
@@ -55,20 +58,33 @@ impl Benchmark for DownloadBinaryBenchmark {
             ));
         }
 
-        Ok(BenchmarkResult::Ok(BenchmarkOkResult {
+        Ok(BenchmarkResult::Ok(Box::new(BenchmarkOkResult {
             time: start.elapsed(),
             iterations: 1,
-        }))
+            ..Default::default()
+        })))
+    }
+
+    fn request_template(
+        &self,
+        _iteration: usize,
+    ) -> Result<Cow<'_, RequestTemplate>, BenchmarkError> {
+        Ok(Cow::Borrowed(&self.template))
     }
 }
 
 pub async fn benchmark_download_binary(
-    iterations: usize,
+    options: RunOptions,
 ) -> Result<BenchmarkResult, BenchmarkError> {
-    info!("benchmark download binary {iterations} iterations");
+    info!("benchmark download binary {options:?}");
 
     let binary_data = Bytes::from(fs::read("/assets/download-binary.png")?);
-    let benchmark = DownloadBinaryBenchmark { binary_data };
+    let url = options.url("/benchmark/download-binary");
+    let benchmark = DownloadBinaryBenchmark {
+        template: RequestTemplate::get(&url)?,
+        url,
+        binary_data,
+    };
 
-    run_requests(iterations, Arc::new(benchmark)).await
+    run_requests(options, Arc::new(benchmark)).await
 }
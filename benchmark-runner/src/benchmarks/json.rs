@@ -0,0 +1,105 @@
+use std::{borrow::Cow, sync::Arc};
+
+use async_trait::async_trait;
+use log::info;
+use reqwest::{Client, Response, StatusCode, header::CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::{
+    Benchmark, BenchmarkError, BenchmarkOkResult, BenchmarkResult, RunOptions,
+    hyper_engine::RequestTemplate, run_requests,
+};
+
+const MESSAGE: &str = "Hello, World!";
+
+struct JsonBenchmark {
+    url: String,
+    template: RequestTemplate,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct JsonMessage<'a> {
+    message: Cow<'a, str>,
+}
+
+#[async_trait]
+impl Benchmark for JsonBenchmark {
+    async fn make_request(
+        &self,
+        client: Client,
+        _iteration: usize,
+    ) -> Result<Response, BenchmarkError> {
+        let response = client.get(&self.url).send().await?;
+        Ok(response)
+    }
+
+    async fn check_response(
+        &self,
+        _iteration: usize,
+        start: Instant,
+        response: Response,
+    ) -> Result<BenchmarkResult, BenchmarkError> {
+        if response.status() != StatusCode::OK {
+            return Ok(BenchmarkResult::InvalidStatusCode(
+                response.status().as_u16(),
+            ));
+        }
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        if !content_type.starts_with("application/json") {
+            return Ok(BenchmarkResult::InvalidResponse(format!(
+                "Expected content type \"application/json\" found \"{content_type}\""
+            )));
+        }
+        let body = response.bytes().await?;
+        let message = match serde_json::from_slice::<JsonMessage>(&body) {
+            Ok(message) => message,
+            Err(err) => {
+                return Ok(BenchmarkResult::InvalidResponse(format!(
+                    "Invalid JSON: {err}"
+                )));
+            }
+        };
+        if message.message != MESSAGE {
+            return Ok(BenchmarkResult::InvalidResponse(format!(
+                "Expected message \"{MESSAGE}\" found \"{}\"",
+                message.message
+            )));
+        }
+
+        Ok(BenchmarkResult::Ok(Box::new(BenchmarkOkResult {
+            time: start.elapsed(),
+            iterations: 1,
+            ..Default::default()
+        })))
+    }
+
+    fn request_template(
+        &self,
+        _iteration: usize,
+    ) -> Result<Cow<'_, RequestTemplate>, BenchmarkError> {
+        Ok(Cow::Borrowed(&self.template))
+    }
+}
+
+pub async fn benchmark_json(options: RunOptions) -> Result<BenchmarkResult, BenchmarkError> {
+    info!("benchmark json {options:?}");
+    let url = options.url("/benchmark/json");
+    let benchmark = JsonBenchmark {
+        template: RequestTemplate::get(&url)?,
+        url,
+    };
+    run_requests(options, Arc::new(benchmark)).await
+}
+
+/// The response body a correct webserver returns.
+pub fn reference_response() -> Result<Vec<u8>, serde_json::Error> {
+    serde_json::to_vec(&JsonMessage {
+        message: Cow::Borrowed(MESSAGE),
+    })
+}
@@ -0,0 +1,166 @@
+use std::{borrow::Cow, sync::Arc};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use log::info;
+use rand::{Rng, SeedableRng, distr::Alphanumeric, rngs::StdRng};
+use reqwest::{Client, Response, StatusCode, header::CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::time::Instant;
+
+use crate::{
+    Benchmark, BenchmarkError, BenchmarkOkResult, BenchmarkResult, RunOptions,
+    hyper_engine::RequestTemplate, run_requests,
+};
+
+/// Distinct documents generated per run, iterations cycle through them.
+const DOCUMENTS: usize = 16;
+
+/// Shape of the generated documents posted to the echo endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonDocumentShape {
+    /// Serialized size the document grows to, in bytes.
+    pub size_bytes: usize,
+    /// Nesting depth of every item in the document.
+    pub depth: usize,
+}
+
+struct JsonEchoBenchmark {
+    url: String,
+    documents: Vec<(Bytes, Value)>,
+    templates: Vec<RequestTemplate>,
+}
+
+#[async_trait]
+impl Benchmark for JsonEchoBenchmark {
+    async fn make_request(
+        &self,
+        client: Client,
+        iteration: usize,
+    ) -> Result<Response, BenchmarkError> {
+        let (body, _) = &self.documents[iteration % DOCUMENTS];
+        let response = client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.clone())
+            .send()
+            .await?;
+        Ok(response)
+    }
+
+    async fn check_response(
+        &self,
+        iteration: usize,
+        start: Instant,
+        response: Response,
+    ) -> Result<BenchmarkResult, BenchmarkError> {
+        if response.status() != StatusCode::OK {
+            return Ok(BenchmarkResult::InvalidStatusCode(
+                response.status().as_u16(),
+            ));
+        }
+        let found: Value = match response.json().await {
+            Ok(json) => json,
+            Err(err) => {
+                return Ok(BenchmarkResult::InvalidResponse(format!(
+                    "Invalid JSON: {err}"
+                )));
+            }
+        };
+        let (_, expected) = &self.documents[iteration % DOCUMENTS];
+        if &found != expected {
+            return Ok(BenchmarkResult::InvalidResponse(
+                "Echoed document differs from the one sent".to_string(),
+            ));
+        }
+
+        Ok(BenchmarkResult::Ok(Box::new(BenchmarkOkResult {
+            time: start.elapsed(),
+            iterations: 1,
+            ..Default::default()
+        })))
+    }
+
+    fn request_template(
+        &self,
+        iteration: usize,
+    ) -> Result<Cow<'_, RequestTemplate>, BenchmarkError> {
+        Ok(Cow::Borrowed(&self.templates[iteration % DOCUMENTS]))
+    }
+}
+
+pub async fn benchmark_json_echo(
+    options: RunOptions,
+    shape: JsonDocumentShape,
+) -> Result<BenchmarkResult, BenchmarkError> {
+    info!("benchmark json echo {shape:?} {options:?}");
+    let url = options.url("/benchmark/json-echo");
+
+    let mut documents = Vec::with_capacity(DOCUMENTS);
+    let mut templates = Vec::with_capacity(DOCUMENTS);
+    for seed in 0..DOCUMENTS {
+        let document = generate_document(seed as u64, shape);
+        let body = Bytes::from(serde_json::to_vec(&document)?);
+        templates.push(RequestTemplate::post(
+            &url,
+            "application/json",
+            body.clone(),
+        )?);
+        documents.push((body, document));
+    }
+
+    let benchmark = JsonEchoBenchmark {
+        url,
+        documents,
+        templates,
+    };
+    run_requests(options, Arc::new(benchmark)).await
+}
+
+/// Response body a correct webserver returns for `body`, used by the calibration null server.
+pub fn reference_response(body: &[u8]) -> Result<Vec<u8>, serde_json::Error> {
+    let document: Value = serde_json::from_slice(body)?;
+    serde_json::to_vec(&document)
+}
+
+/// Document of nested items, appended until its serialized size reaches `shape.size_bytes`.
+/// Numbers are integers so that every JSON implementation echoes them back unchanged.
+pub fn generate_document(seed: u64, shape: JsonDocumentShape) -> Value {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut items = vec![];
+    let mut size = 0;
+    while size < shape.size_bytes {
+        let item = generate_item(&mut rng, shape.depth.max(1));
+        size += item.to_string().len() + 1;
+        items.push(item);
+    }
+
+    let mut document = Map::new();
+    document.insert("seed".to_string(), Value::from(seed));
+    document.insert("items".to_string(), Value::Array(items));
+    Value::Object(document)
+}
+
+fn generate_item(rng: &mut StdRng, depth: usize) -> Value {
+    let name_len = rng.random_range(8..24);
+    let name: String = rng
+        .sample_iter(Alphanumeric)
+        .take(name_len)
+        .map(char::from)
+        .collect();
+    let scores: Vec<Value> = (0..4)
+        .map(|_| Value::from(rng.random_range(-1_000_000i64..1_000_000)))
+        .collect();
+
+    let mut item = Map::new();
+    item.insert("id".to_string(), Value::from(rng.random::<u32>()));
+    item.insert("name".to_string(), Value::String(name));
+    item.insert("active".to_string(), Value::Bool(rng.random_bool(0.5)));
+    item.insert("scores".to_string(), Value::Array(scores));
+    if depth > 1 {
+        item.insert("child".to_string(), generate_item(rng, depth - 1));
+    }
+    Value::Object(item)
+}
@@ -0,0 +1,461 @@
+use std::{
+    borrow::Cow,
+    fs::{self, File},
+    io::{ErrorKind, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use log::info;
+use rand::{RngCore, SeedableRng, rngs::StdRng};
+use reqwest::{
+    Client, Response, StatusCode,
+    header::{CONTENT_RANGE, CONTENT_TYPE, RANGE},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::time::Instant;
+
+use crate::{
+    Benchmark, BenchmarkError, BenchmarkOkResult, BenchmarkResult, RunOptions,
+    hyper_engine::RequestTemplate, run_requests,
+};
+
+pub const LARGE_FILES_DIR: &str = "/assets/large-files";
+
+/// Sizes of the generated files, in bytes.
+pub const LARGE_FILE_SIZES: [u64; 2] = [64 * 1024 * 1024, 1024 * 1024 * 1024];
+
+const GENERATE_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Boundary of the `multipart/byteranges` responses built by the null server.
+pub const BYTERANGES_BOUNDARY: &str = "webserver-benchmarks-byteranges-boundary";
+
+/// Which generated file the large file benchmark downloads and which part of it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct LargeFileOptions {
+    /// Size of the file, one of [`LARGE_FILE_SIZES`].
+    pub size_bytes: u64,
+    /// Bytes requested with a `Range` header, the whole file when `None`.
+    pub range: Option<ByteRange>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "type"
+)]
+pub enum ByteRange {
+    /// `bytes=start-end`
+    Single { start: u64, length: u64 },
+    /// `bytes=-length`, the last `length` bytes of the file.
+    Suffix { length: u64 },
+    /// `count` ranges of `length` bytes spread evenly over the file, answered with a
+    /// `multipart/byteranges` body.
+    Multi { count: u64, length: u64 },
+}
+
+impl ByteRange {
+    /// Inclusive first and last byte of every range within a file of `size` bytes, an error for
+    /// empty ranges and ranges that do not fit the file.
+    fn resolve(&self, size: u64) -> Result<Vec<(u64, u64)>, std::io::Error> {
+        let length = match *self {
+            ByteRange::Single { length, .. }
+            | ByteRange::Suffix { length }
+            | ByteRange::Multi { length, .. } => length,
+        };
+        let invalid = || {
+            std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("range {self:?} does not fit a file of {size} bytes"),
+            )
+        };
+        if length == 0 || length > size {
+            return Err(invalid());
+        }
+        match *self {
+            ByteRange::Single { start, length } => {
+                if start > size - length {
+                    return Err(invalid());
+                }
+                Ok(vec![(start, start + length - 1)])
+            }
+            ByteRange::Suffix { length } => Ok(vec![(size - length, size - 1)]),
+            ByteRange::Multi { count, length } => {
+                if count == 0 || length > size / count {
+                    return Err(invalid());
+                }
+                Ok((0..count)
+                    .map(|i| {
+                        let start = i * (size / count);
+                        (start, start + length - 1)
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    fn header(&self, size: u64) -> Result<String, std::io::Error> {
+        match *self {
+            ByteRange::Suffix { length } => Ok(format!("bytes=-{length}")),
+            _ => {
+                let ranges: Vec<String> = self
+                    .resolve(size)?
+                    .iter()
+                    .map(|(start, end)| format!("{start}-{end}"))
+                    .collect();
+                Ok(format!("bytes={}", ranges.join(",")))
+            }
+        }
+    }
+}
+
+enum Expected {
+    Whole {
+        length: u64,
+        sha256: String,
+    },
+    /// Content range header value and content of every requested range, in request order.
+    Ranges(Vec<(String, Bytes)>),
+}
+
+struct LargeFileBenchmark {
+    url: String,
+    template: RequestTemplate,
+    range: Option<String>,
+    expected: Expected,
+}
+
+#[async_trait]
+impl Benchmark for LargeFileBenchmark {
+    async fn make_request(
+        &self,
+        client: Client,
+        _iteration: usize,
+    ) -> Result<Response, BenchmarkError> {
+        let mut request = client.get(&self.url);
+        if let Some(range) = &self.range {
+            request = request.header(RANGE, range);
+        }
+        let response = request.send().await?;
+        Ok(response)
+    }
+
+    async fn check_response(
+        &self,
+        _iteration: usize,
+        start: Instant,
+        response: Response,
+    ) -> Result<BenchmarkResult, BenchmarkError> {
+        match &self.expected {
+            Expected::Whole { length, sha256 } => {
+                check_whole(start, response, *length, sha256).await
+            }
+            Expected::Ranges(ranges) => check_ranges(start, response, ranges).await,
+        }
+    }
+
+    fn request_template(
+        &self,
+        _iteration: usize,
+    ) -> Result<Cow<'_, RequestTemplate>, BenchmarkError> {
+        Ok(Cow::Borrowed(&self.template))
+    }
+}
+
+/// Hashes the body chunk by chunk as it arrives, so that the file is never held in memory.
+async fn check_whole(
+    start: Instant,
+    mut response: Response,
+    length: u64,
+    sha256: &str,
+) -> Result<BenchmarkResult, BenchmarkError> {
+    if response.status() != StatusCode::OK {
+        return Ok(BenchmarkResult::InvalidStatusCode(
+            response.status().as_u16(),
+        ));
+    }
+
+    let mut hasher = Sha256::new();
+    let mut found_length = 0;
+    while let Some(chunk) = response.chunk().await? {
+        found_length += chunk.len() as u64;
+        hasher.update(&chunk);
+    }
+    if found_length != length {
+        return Ok(BenchmarkResult::InvalidResponse(format!(
+            "Expected bytes length {length} found bytes len {found_length}"
+        )));
+    }
+    let found_sha256 = format!("{:x}", hasher.finalize());
+    if found_sha256 != sha256 {
+        return Ok(BenchmarkResult::InvalidResponse(format!(
+            "Expected SHA-256 {sha256} found {found_sha256}"
+        )));
+    }
+    Ok(ok(start))
+}
+
+async fn check_ranges(
+    start: Instant,
+    response: Response,
+    expected: &[(String, Bytes)],
+) -> Result<BenchmarkResult, BenchmarkError> {
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        return Ok(BenchmarkResult::InvalidStatusCode(
+            response.status().as_u16(),
+        ));
+    }
+
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string()
+    };
+    let content_range = header(CONTENT_RANGE);
+    let content_type = header(CONTENT_TYPE);
+    let body = response.bytes().await?;
+
+    let found = if let [(_, _)] = expected {
+        vec![(content_range, &body[..])]
+    } else {
+        let Some(boundary) = content_type
+            .strip_prefix("multipart/byteranges")
+            .and_then(|params| params.split_once("boundary="))
+            .map(|(_, boundary)| boundary.trim_matches('"'))
+        else {
+            return Ok(BenchmarkResult::InvalidResponse(format!(
+                "Expected content type \"multipart/byteranges\" found \"{content_type}\""
+            )));
+        };
+        match byteranges_parts(boundary, &body) {
+            Ok(parts) => parts,
+            Err(err) => return Ok(BenchmarkResult::InvalidResponse(err)),
+        }
+    };
+
+    if found.len() != expected.len() {
+        return Ok(BenchmarkResult::InvalidResponse(format!(
+            "Expected {} ranges found {}",
+            expected.len(),
+            found.len()
+        )));
+    }
+    for ((expected_range, expected_bytes), (found_range, found_bytes)) in expected.iter().zip(found)
+    {
+        if *expected_range != found_range {
+            return Ok(BenchmarkResult::InvalidResponse(format!(
+                "Expected content range \"{expected_range}\" found \"{found_range}\""
+            )));
+        }
+        if expected_bytes[..] != *found_bytes {
+            return Ok(BenchmarkResult::InvalidResponse(format!(
+                "Bytes data mismatch in range {found_range}"
+            )));
+        }
+    }
+    Ok(ok(start))
+}
+
+fn ok(start: Instant) -> BenchmarkResult {
+    BenchmarkResult::Ok(Box::new(BenchmarkOkResult {
+        time: start.elapsed(),
+        iterations: 1,
+        ..Default::default()
+    }))
+}
+
+pub async fn benchmark_large_file(
+    options: RunOptions,
+    large_file_options: LargeFileOptions,
+) -> Result<BenchmarkResult, BenchmarkError> {
+    info!("benchmark large file {large_file_options:?} {options:?}");
+
+    let size = large_file_options.size_bytes;
+    let path = large_file_path(size);
+    let (range, expected) = match large_file_options.range {
+        None => (
+            None,
+            Expected::Whole {
+                length: size,
+                sha256: fs::read_to_string(sha256_path(size))?.trim().to_string(),
+            },
+        ),
+        Some(range) => {
+            let mut file = File::open(&path)?;
+            let mut ranges = vec![];
+            for (start, end) in range.resolve(size)? {
+                ranges.push((
+                    content_range(start, end, size),
+                    Bytes::from(read_range(&mut file, start, end)?),
+                ));
+            }
+            (Some(range.header(size)?), Expected::Ranges(ranges))
+        }
+    };
+
+    let url = options.url(&format!("/benchmark/large-file/{size}.bin"));
+    let mut template = RequestTemplate::get(&url)?;
+    if let Some(range) = &range {
+        template = template.with_header(RANGE, range)?;
+    }
+    let benchmark = LargeFileBenchmark {
+        url,
+        template,
+        range,
+        expected,
+    };
+    run_requests(options, Arc::new(benchmark)).await
+}
+
+pub fn large_file_path(size: u64) -> PathBuf {
+    PathBuf::from(LARGE_FILES_DIR).join(format!("{size}.bin"))
+}
+
+fn sha256_path(size: u64) -> PathBuf {
+    PathBuf::from(LARGE_FILES_DIR).join(format!("{size}.bin.sha256"))
+}
+
+/// Writes a file of seeded random bytes for every size in [`LARGE_FILE_SIZES`] into the assets
+/// volume, each next to a `.sha256` file holding its hex encoded digest. Files left by an earlier
+/// run are kept, since they are identical and take a while to write.
+pub fn generate_large_files() -> Result<(), std::io::Error> {
+    fs::create_dir_all(LARGE_FILES_DIR)?;
+    for size in LARGE_FILE_SIZES {
+        let path = large_file_path(size);
+        let existing = fs::metadata(&path).map(|metadata| metadata.len()).ok();
+        if existing == Some(size) && sha256_path(size).exists() {
+            continue;
+        }
+
+        info!("generating large file {}", path.display());
+        let mut rng = StdRng::seed_from_u64(size);
+        let mut file = File::create(&path)?;
+        let mut hasher = Sha256::new();
+        let mut chunk = vec![0u8; GENERATE_CHUNK_SIZE];
+        let mut remaining = size;
+        while remaining > 0 {
+            let chunk = &mut chunk[..remaining.min(GENERATE_CHUNK_SIZE as u64) as usize];
+            rng.fill_bytes(chunk);
+            hasher.update(&chunk[..]);
+            file.write_all(chunk)?;
+            remaining -= chunk.len() as u64;
+        }
+        fs::write(sha256_path(size), format!("{:x}\n", hasher.finalize()))?;
+    }
+    Ok(())
+}
+
+/// Bytes `start..=end` of `file`.
+pub fn read_range(file: &mut File, start: u64, end: u64) -> Result<Vec<u8>, std::io::Error> {
+    let mut bytes = vec![0u8; (end - start + 1) as usize];
+    file.seek(SeekFrom::Start(start))?;
+    file.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+pub fn content_range(start: u64, end: u64, size: u64) -> String {
+    format!("bytes {start}-{end}/{size}")
+}
+
+/// Inclusive first and last byte of every range of a `Range` header value, `None` when the
+/// header is malformed or a range is not satisfiable.
+pub fn parse_range(header: &str, size: u64) -> Option<Vec<(u64, u64)>> {
+    let ranges = header.trim().strip_prefix("bytes=")?;
+    // nothing of an empty file is satisfiable
+    let last = size.checked_sub(1)?;
+    ranges
+        .split(',')
+        .map(|range| {
+            let (start, end) = range.trim().split_once('-')?;
+            let (start, end) = match (start, end) {
+                ("", length) => (size.checked_sub(length.parse().ok()?)?, last),
+                (start, "") => (start.parse().ok()?, last),
+                (start, end) => (start.parse().ok()?, end.parse::<u64>().ok()?.min(last)),
+            };
+            (start <= end && end < size).then_some((start, end))
+        })
+        .collect()
+}
+
+/// `multipart/byteranges` body with one part per range, each given as its inclusive first and
+/// last byte and its content.
+pub fn byteranges_body(parts: &[(u64, u64, Vec<u8>)], size: u64) -> Vec<u8> {
+    let mut body = vec![];
+    for (start, end, bytes) in parts {
+        body.extend_from_slice(
+            format!(
+                "--{BYTERANGES_BOUNDARY}\r\n\
+                 Content-Type: application/octet-stream\r\n\
+                 Content-Range: {}\r\n\r\n",
+                content_range(*start, *end, size)
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(bytes);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{BYTERANGES_BOUNDARY}--\r\n").as_bytes());
+    body
+}
+
+/// Content range and content of every part of a `multipart/byteranges` body. Part lengths are
+/// taken from their `Content-Range` headers so that the binary content is never scanned for the
+/// boundary.
+fn byteranges_parts<'a>(boundary: &str, body: &'a [u8]) -> Result<Vec<(String, &'a [u8])>, String> {
+    let delimiter = format!("--{boundary}");
+    let mut parts = vec![];
+    let mut rest = body;
+    loop {
+        while let Some(stripped) = rest.strip_prefix(b"\r\n") {
+            rest = stripped;
+        }
+        rest = rest
+            .strip_prefix(delimiter.as_bytes())
+            .ok_or("Expected multipart boundary")?;
+        if rest.starts_with(b"--") {
+            return Ok(parts);
+        }
+        rest = rest
+            .strip_prefix(b"\r\n")
+            .ok_or("Expected line break after multipart boundary")?;
+
+        let mut headers = [httparse::EMPTY_HEADER; 8];
+        let (consumed, headers) = match httparse::parse_headers(rest, &mut headers) {
+            Ok(httparse::Status::Complete(parsed)) => parsed,
+            Ok(httparse::Status::Partial) => return Err("Truncated part headers".to_string()),
+            Err(err) => return Err(format!("Invalid part headers: {err}")),
+        };
+        let content_range = headers
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case(CONTENT_RANGE.as_str()))
+            .and_then(|header| std::str::from_utf8(header.value).ok())
+            .ok_or("Part without content range")?
+            .to_string();
+        let length = content_range
+            .strip_prefix("bytes ")
+            .and_then(|range| range.split_once('/'))
+            .and_then(|(range, _)| range.split_once('-'))
+            .and_then(|(start, end)| {
+                end.parse::<u64>()
+                    .ok()?
+                    .checked_sub(start.parse::<u64>().ok()?)?
+                    .checked_add(1)
+            })
+            .ok_or_else(|| format!("Invalid content range \"{content_range}\""))?;
+
+        rest = &rest[consumed..];
+        let content = rest
+            .get(..length as usize)
+            .ok_or("Truncated part content")?;
+        rest = &rest[length as usize..];
+        parts.push((content_range, content));
+    }
+}
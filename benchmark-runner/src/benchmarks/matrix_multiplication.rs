@@ -1,22 +1,318 @@
-use std::sync::Arc;
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
+};
 
 use async_trait::async_trait;
+use bytes::Bytes;
 use log::info;
 use rand::{Rng, SeedableRng, rngs::StdRng};
-use reqwest::{Client, Response};
+use reqwest::{Client, Response, header::CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use tokio::time::Instant;
 
-use crate::{Benchmark, BenchmarkError, BenchmarkOkResult, BenchmarkResult, run_requests};
+use crate::{
+    Benchmark, BenchmarkError, BenchmarkOkResult, BenchmarkResult, RunOptions,
+    hyper_engine::RequestTemplate, run_requests,
+};
 
 type Matrix = Vec<Vec<f64>>;
 
-const ROWS: usize = 101;
-const COLUMNS: usize = 101;
+/// Content type of the binary encoding: each matrix is its row and column count as little-endian
+/// u32s followed by its values as little-endian f64s, row by row. Requests carry both matrices
+/// back to back, responses the result only.
+pub const BINARY_CONTENT_TYPE: &str = "application/x-matrix-f64";
+
+/// Largest number of rows or columns a binary matrix may declare.
+const MAX_BINARY_DIMENSION: usize = 4096;
+
+#[derive(Error, Debug)]
+pub enum MatrixCodecError {
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid binary matrix: {0}")]
+    Binary(String),
+
+    #[error("Unsupported content type \"{0}\"")]
+    UnsupportedContentType(String),
+}
+
+/// Wire format of matrix multiplication requests and responses, negotiated via `Content-Type`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum MatrixEncoding {
+    #[default]
+    Json,
+    Binary,
+}
+
+impl MatrixEncoding {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            MatrixEncoding::Json => "application/json",
+            MatrixEncoding::Binary => BINARY_CONTENT_TYPE,
+        }
+    }
+
+    fn from_content_type(content_type: &str) -> Result<Self, MatrixCodecError> {
+        if content_type.starts_with("application/json") {
+            Ok(MatrixEncoding::Json)
+        } else if content_type.starts_with(BINARY_CONTENT_TYPE) {
+            Ok(MatrixEncoding::Binary)
+        } else {
+            Err(MatrixCodecError::UnsupportedContentType(
+                content_type.to_string(),
+            ))
+        }
+    }
+
+    fn encode_request(
+        &self,
+        matrix1: &Matrix,
+        matrix2: &Matrix,
+    ) -> Result<Bytes, MatrixCodecError> {
+        match self {
+            MatrixEncoding::Json => Ok(Bytes::from(serde_json::to_vec(
+                &MatrixMultiplicationRequest { matrix1, matrix2 },
+            )?)),
+            MatrixEncoding::Binary => {
+                let mut body = Vec::new();
+                write_binary_matrix(&mut body, matrix1)?;
+                write_binary_matrix(&mut body, matrix2)?;
+                Ok(Bytes::from(body))
+            }
+        }
+    }
+
+    fn decode_request(&self, body: &[u8]) -> Result<(Matrix, Matrix), MatrixCodecError> {
+        match self {
+            MatrixEncoding::Json => {
+                let request: OwnedMatrixMultiplicationRequest = serde_json::from_slice(body)?;
+                Ok((request.matrix1, request.matrix2))
+            }
+            MatrixEncoding::Binary => {
+                let mut input = body;
+                let matrix1 = read_binary_matrix(&mut input)?;
+                let matrix2 = read_binary_matrix(&mut input)?;
+                expect_end(input)?;
+                Ok((matrix1, matrix2))
+            }
+        }
+    }
+
+    fn encode_response(&self, result: Matrix) -> Result<Vec<u8>, MatrixCodecError> {
+        match self {
+            MatrixEncoding::Json => Ok(serde_json::to_vec(&MatrixMultiplicationResponse {
+                result,
+            })?),
+            MatrixEncoding::Binary => {
+                let mut body = Vec::new();
+                write_binary_matrix(&mut body, &result)?;
+                Ok(body)
+            }
+        }
+    }
+
+    fn decode_response(&self, body: &[u8]) -> Result<Matrix, MatrixCodecError> {
+        match self {
+            MatrixEncoding::Json => {
+                let response: MatrixMultiplicationResponse = serde_json::from_slice(body)?;
+                Ok(response.result)
+            }
+            MatrixEncoding::Binary => {
+                let mut input = body;
+                let result = read_binary_matrix(&mut input)?;
+                expect_end(input)?;
+                Ok(result)
+            }
+        }
+    }
+}
+
+fn write_binary_matrix(body: &mut Vec<u8>, matrix: &Matrix) -> Result<(), MatrixCodecError> {
+    let rows = matrix.len();
+    let columns = matrix.first().map_or(0, Vec::len);
+    let dimension = |value: usize| {
+        u32::try_from(value)
+            .map_err(|_| MatrixCodecError::Binary(format!("dimension {value} exceeds u32")))
+    };
+    body.extend_from_slice(&dimension(rows)?.to_le_bytes());
+    body.extend_from_slice(&dimension(columns)?.to_le_bytes());
+    for row in matrix {
+        if row.len() != columns {
+            return Err(MatrixCodecError::Binary("ragged matrix".to_string()));
+        }
+        for value in row {
+            body.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    Ok(())
+}
+
+fn read_binary_matrix(input: &mut &[u8]) -> Result<Matrix, MatrixCodecError> {
+    let rows = read_u32(input)? as usize;
+    let columns = read_u32(input)? as usize;
+    // a matrix without columns would otherwise pass the length check with any number of rows
+    if !(1..=MAX_BINARY_DIMENSION).contains(&rows) || !(1..=MAX_BINARY_DIMENSION).contains(&columns)
+    {
+        return Err(MatrixCodecError::Binary(format!(
+            "{rows}x{columns} matrix is empty or exceeds {MAX_BINARY_DIMENSION} rows or columns"
+        )));
+    }
+    let len = rows
+        .checked_mul(columns)
+        .and_then(|values| values.checked_mul(8))
+        .filter(|len| *len <= input.len())
+        .ok_or_else(|| {
+            MatrixCodecError::Binary(format!("{rows}x{columns} matrix exceeds the body"))
+        })?;
+    let (values, rest) = input.split_at(len);
+    *input = rest;
+
+    let mut chunks = values.chunks_exact(8);
+    Ok((0..rows)
+        .map(|_| {
+            (&mut chunks)
+                .take(columns)
+                .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap_or_default()))
+                .collect()
+        })
+        .collect())
+}
+
+fn read_u32(input: &mut &[u8]) -> Result<u32, MatrixCodecError> {
+    let Some((value, rest)) = input.split_first_chunk::<4>() else {
+        return Err(MatrixCodecError::Binary("truncated header".to_string()));
+    };
+    *input = rest;
+    Ok(u32::from_le_bytes(*value))
+}
+
+fn expect_end(input: &[u8]) -> Result<(), MatrixCodecError> {
+    if input.is_empty() {
+        Ok(())
+    } else {
+        Err(MatrixCodecError::Binary(format!(
+            "{} trailing bytes",
+            input.len()
+        )))
+    }
+}
+
+/// Parameters of the matrix multiplication workload, multiplying a `rows` x `inner` matrix by an
+/// `inner` x `columns` matrix.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct MatrixMultiplicationOptions {
+    pub rows: usize,
+    pub inner: usize,
+    pub columns: usize,
+    pub tolerance: FloatTolerance,
+    pub encoding: MatrixEncoding,
+}
+
+impl MatrixMultiplicationOptions {
+    pub fn square(size: usize, tolerance: FloatTolerance) -> Self {
+        Self {
+            rows: size,
+            inner: size,
+            columns: size,
+            tolerance,
+            encoding: MatrixEncoding::Json,
+        }
+    }
+
+    pub fn with_encoding(mut self, encoding: MatrixEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+}
+
+/// How far a returned value may be from the reference result. A value passes when it is within
+/// any one of the bounds, so all zero only accepts exact matches.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct FloatTolerance {
+    pub absolute: f64,
+    pub relative: f64,
+    pub ulps: u64,
+}
+
+impl FloatTolerance {
+    pub const EXACT: Self = Self {
+        absolute: 0.0,
+        relative: 0.0,
+        ulps: 0,
+    };
+
+    /// Allows for a different summation order, SIMD or BLAS on the server.
+    pub const DEFAULT: Self = Self {
+        absolute: 1e-9,
+        relative: 1e-9,
+        ulps: 16,
+    };
+
+    fn accepts(&self, error: &FloatError) -> bool {
+        error.absolute <= self.absolute
+            || error.relative <= self.relative
+            || error.ulps <= self.ulps
+    }
+}
+
+/// Distance between a returned value and the reference result.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FloatError {
+    pub absolute: f64,
+    pub relative: f64,
+    pub ulps: u64,
+}
+
+impl FloatError {
+    fn between(expected: f64, found: f64) -> Self {
+        let absolute = (found - expected).abs();
+        let relative = if absolute == 0.0 {
+            0.0
+        } else {
+            absolute / expected.abs()
+        };
+        let ulps = if expected.is_nan() || found.is_nan() {
+            u64::MAX
+        } else {
+            ordered_bits(expected).abs_diff(ordered_bits(found))
+        };
+        Self {
+            absolute,
+            relative,
+            ulps,
+        }
+    }
+
+    /// Largest error in each measure of `self` and `other`.
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            absolute: self.absolute.max(other.absolute),
+            relative: self.relative.max(other.relative),
+            ulps: self.ulps.max(other.ulps),
+        }
+    }
+}
+
+/// Maps a float's bits so that adjacent floats differ by one, across zero as well.
+fn ordered_bits(value: f64) -> i64 {
+    let bits = value.to_bits() as i64;
+    if bits < 0 { i64::MIN - bits } else { bits }
+}
 
 struct MatrixMultiplicationBenchmark {
-    matrices: Vec<Matrix>,
+    url: String,
+    /// Requests encoded up front so that the load engines only clone them.
+    templates: Vec<RequestTemplate>,
     expected: Vec<Matrix>,
+    options: MatrixMultiplicationOptions,
+    max_error: Mutex<FloatError>,
 }
 
 #[derive(Serialize, Debug)]
@@ -28,6 +324,13 @@ struct MatrixMultiplicationRequest<'a> {
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
+struct OwnedMatrixMultiplicationRequest {
+    matrix1: Matrix,
+    matrix2: Matrix,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
 struct MatrixMultiplicationResponse {
     result: Matrix,
 }
@@ -39,12 +342,10 @@ impl Benchmark for MatrixMultiplicationBenchmark {
         client: Client,
         iteration: usize,
     ) -> Result<Response, BenchmarkError> {
-        let matrix1 = &self.matrices[iteration];
-        let matrix2 = &self.matrices[iteration + 1];
-        let request = MatrixMultiplicationRequest { matrix1, matrix2 };
         let response = client
-            .post("http://web:8000/benchmark/matrix-multiplication")
-            .json(&request)
+            .post(&self.url)
+            .header(CONTENT_TYPE, self.options.encoding.content_type())
+            .body(self.templates[iteration].body().clone())
             .send()
             .await?;
         Ok(response)
@@ -56,26 +357,36 @@ impl Benchmark for MatrixMultiplicationBenchmark {
         start: Instant,
         response: Response,
     ) -> Result<BenchmarkResult, BenchmarkError> {
-        let response: MatrixMultiplicationResponse = match response.json().await {
-            Ok(json) => json,
-            Err(err) => {
+        let encoding = self.options.encoding;
+        if encoding == MatrixEncoding::Binary {
+            let content_type = response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            if !content_type.starts_with(BINARY_CONTENT_TYPE) {
                 return Ok(BenchmarkResult::InvalidResponse(format!(
-                    "Invalid JSON: {err}"
+                    "Expected content type \"{BINARY_CONTENT_TYPE}\" found \"{content_type}\""
                 )));
             }
+        }
+        let body = response.bytes().await?;
+        let found = match encoding.decode_response(&body) {
+            Ok(found) => found,
+            Err(err) => return Ok(BenchmarkResult::InvalidResponse(err.to_string())),
         };
-        let found = response.result;
         let expected = &self.expected[iteration];
 
-        if found.len() != ROWS {
+        if found.len() != self.options.rows {
             return Ok(BenchmarkResult::InvalidResponse(format!(
                 "Expected {} rows found {} rows",
-                ROWS,
+                self.options.rows,
                 found.len()
             )));
         }
 
-        for row in 0..ROWS {
+        let mut max_error = FloatError::default();
+        for row in 0..self.options.rows {
             let expected_row = &expected[row];
             let found_row = &found[row];
 
@@ -87,45 +398,108 @@ impl Benchmark for MatrixMultiplicationBenchmark {
                 )));
             }
 
-            for column in 0..COLUMNS {
+            for column in 0..self.options.columns {
                 let exected_value = expected_row[column];
                 let found_value = found_row[column];
-                if exected_value != found_value {
+                let error = FloatError::between(exected_value, found_value);
+                if !self.options.tolerance.accepts(&error) {
                     return Ok(BenchmarkResult::InvalidResponse(format!(
-                        "Expected value {} found {}",
-                        exected_value, found_value
+                        "Expected value {} found {} at [{row}][{column}], off by {} ({} ULPs)",
+                        exected_value, found_value, error.absolute, error.ulps
                     )));
                 }
+                max_error = max_error.max(error);
             }
         }
+        if let Ok(mut run_max_error) = self.max_error.lock() {
+            *run_max_error = run_max_error.max(max_error);
+        }
 
-        Ok(BenchmarkResult::Ok(BenchmarkOkResult {
+        Ok(BenchmarkResult::Ok(Box::new(BenchmarkOkResult {
             time: start.elapsed(),
             iterations: 1,
-        }))
+            ..Default::default()
+        })))
+    }
+
+    fn request_template(
+        &self,
+        iteration: usize,
+    ) -> Result<Cow<'_, RequestTemplate>, BenchmarkError> {
+        Ok(Cow::Borrowed(&self.templates[iteration]))
     }
 }
 
 pub async fn benchmark_matrix_multiplication(
-    iterations: usize,
+    options: RunOptions,
+    matrix_options: MatrixMultiplicationOptions,
 ) -> Result<BenchmarkResult, BenchmarkError> {
-    info!("benchmark matrix multiplication {iterations} iterations");
+    info!("benchmark matrix multiplication {matrix_options:?} {options:?}");
 
-    let mut matrices: Vec<Matrix> = vec![];
-    for i in 0..(iterations + 1) {
-        matrices.push(generate_matrix(i as u64, ROWS, COLUMNS));
-    }
+    let iterations = options.load_profile.iterations();
+    let url = options.url("/benchmark/matrix-multiplication");
+    let encoding = matrix_options.encoding;
 
+    let mut templates: Vec<RequestTemplate> = vec![];
     let mut expected: Vec<Matrix> = vec![];
     for i in 0..iterations {
-        let matrix1 = &matrices[i];
-        let matrix2 = &matrices[i + 1];
-        expected.push(matrix_multiply(matrix1, matrix2));
+        let (matrix1, matrix2) = request_matrices(&matrix_options, i);
+        let body = encoding.encode_request(&matrix1, &matrix2)?;
+        templates.push(RequestTemplate::post(&url, encoding.content_type(), body)?);
+        expected.push(matrix_multiply(&matrix1, &matrix2));
+    }
+
+    let benchmark = Arc::new(MatrixMultiplicationBenchmark {
+        url,
+        templates,
+        expected,
+        options: matrix_options,
+        max_error: Mutex::new(FloatError::default()),
+    });
+
+    let mut result = run_requests(options, benchmark.clone()).await?;
+    if let (BenchmarkResult::Ok(result), Ok(max_error)) = (&mut result, benchmark.max_error.lock())
+    {
+        result.max_float_error = Some(*max_error);
     }
+    Ok(result)
+}
+
+/// Content type and body a correct webserver returns for a request of `content_type`, used by
+/// the calibration null server.
+pub fn reference_response(
+    content_type: &str,
+    body: &[u8],
+) -> Result<(&'static str, Vec<u8>), MatrixCodecError> {
+    let encoding = MatrixEncoding::from_content_type(content_type)?;
+    let (matrix1, matrix2) = encoding.decode_request(body)?;
+    let result = matrix_multiply(&matrix1, &matrix2);
+    Ok((encoding.content_type(), encoding.encode_response(result)?))
+}
 
-    let benchmark = MatrixMultiplicationBenchmark { matrices, expected };
+/// Request and response bodies of the first `iterations` requests of a workload, so that the
+/// calibration null server can answer them without multiplying.
+pub fn reference_exchanges(
+    options: &MatrixMultiplicationOptions,
+    iterations: usize,
+) -> Result<Vec<(Bytes, Bytes)>, MatrixCodecError> {
+    let encoding = options.encoding;
+    (0..iterations)
+        .map(|i| {
+            let (matrix1, matrix2) = request_matrices(options, i);
+            let request = encoding.encode_request(&matrix1, &matrix2)?;
+            let response = encoding.encode_response(matrix_multiply(&matrix1, &matrix2))?;
+            Ok((request, Bytes::from(response)))
+        })
+        .collect()
+}
 
-    run_requests(iterations, Arc::new(benchmark)).await
+/// Iteration i multiplies the matrices seeded with i and i + 1.
+fn request_matrices(options: &MatrixMultiplicationOptions, iteration: usize) -> (Matrix, Matrix) {
+    (
+        generate_matrix(iteration as u64, options.rows, options.inner),
+        generate_matrix((iteration + 1) as u64, options.inner, options.columns),
+    )
 }
 
 fn new_matrix(rows: usize, columns: usize) -> Matrix {
@@ -137,7 +511,7 @@ fn matrix_multiply(matrix1: &Matrix, matrix2: &Matrix) -> Matrix {
     let n = matrix1[0].len();
     let p = matrix2[0].len();
 
-    let mut b2 = new_matrix(n, p);
+    let mut b2 = new_matrix(p, n);
     for (i, row) in matrix2.iter().enumerate() {
         for (j, x) in row.iter().enumerate() {
             b2[j][i] = *x;
@@ -1,3 +1,14 @@
+pub mod compression;
+pub mod conditional_get;
+pub mod database;
+pub mod delay;
 pub mod download_binary;
+pub mod json;
+pub mod json_echo;
+pub mod large_file;
 pub mod matrix_multiplication;
 pub mod plaintext;
+pub mod sse;
+pub mod static_files;
+pub mod upload_binary;
+pub mod websocket;
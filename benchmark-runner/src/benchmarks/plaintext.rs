@@ -1,13 +1,19 @@
-use std::sync::Arc;
+use std::{borrow::Cow, sync::Arc};
 
 use async_trait::async_trait;
 use log::info;
 use reqwest::{Client, Response, StatusCode};
 use tokio::time::Instant;
 
-use crate::{Benchmark, BenchmarkError, BenchmarkOkResult, BenchmarkResult, run_requests};
+use crate::{
+    Benchmark, BenchmarkError, BenchmarkOkResult, BenchmarkResult, RunOptions,
+    hyper_engine::RequestTemplate, run_requests,
+};
 
-struct PlaintextBenchmark {}
+struct PlaintextBenchmark {
+    url: String,
+    template: RequestTemplate,
+}
 
 #[async_trait]
 impl Benchmark for PlaintextBenchmark {
@@ -16,10 +22,7 @@ impl Benchmark for PlaintextBenchmark {
         client: Client,
         _iteration: usize,
     ) -> Result<Response, BenchmarkError> {
-        let response = client
-            .get("http://web:8000/benchmark/plain-text")
-            .send()
-            .await?;
+        let response = client.get(&self.url).send().await?;
         Ok(response)
     }
 
@@ -41,15 +44,27 @@ impl Benchmark for PlaintextBenchmark {
             )));
         }
 
-        Ok(BenchmarkResult::Ok(BenchmarkOkResult {
+        Ok(BenchmarkResult::Ok(Box::new(BenchmarkOkResult {
             time: start.elapsed(),
             iterations: 1,
-        }))
+            ..Default::default()
+        })))
+    }
+
+    fn request_template(
+        &self,
+        _iteration: usize,
+    ) -> Result<Cow<'_, RequestTemplate>, BenchmarkError> {
+        Ok(Cow::Borrowed(&self.template))
     }
 }
 
-pub async fn benchmark_plaintext(iterations: usize) -> Result<BenchmarkResult, BenchmarkError> {
-    info!("benchmark plaintext {iterations} iterations");
-    let benchmark = PlaintextBenchmark {};
-    run_requests(iterations, Arc::new(benchmark)).await
+pub async fn benchmark_plaintext(options: RunOptions) -> Result<BenchmarkResult, BenchmarkError> {
+    info!("benchmark plaintext {options:?}");
+    let url = options.url("/benchmark/plain-text");
+    let benchmark = PlaintextBenchmark {
+        template: RequestTemplate::get(&url)?,
+        url,
+    };
+    run_requests(options, Arc::new(benchmark)).await
 }
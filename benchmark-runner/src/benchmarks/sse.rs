@@ -0,0 +1,312 @@
+use std::{
+    borrow::Cow,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+};
+
+use async_trait::async_trait;
+use log::info;
+use reqwest::{Client, Response, StatusCode, header::CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::{
+    Benchmark, BenchmarkError, BenchmarkOkResult, BenchmarkResult, RunOptions,
+    hyper_engine::RequestTemplate, run_requests,
+};
+
+/// How many events the webserver emits on every stream and how far apart.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct SseOptions {
+    pub events: usize,
+    pub interval_ms: u64,
+}
+
+/// Event timing over the valid streams of an SSE run.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SseStats {
+    pub streams: usize,
+    pub events: u64,
+    /// Time from sending the request to receiving the first event.
+    pub time_to_first_event_mean_ms: f64,
+    pub time_to_first_event_max_ms: f64,
+    /// How far the gaps between consecutive events strayed from the interval.
+    pub jitter_mean_ms: f64,
+    pub jitter_max_ms: f64,
+    /// Most streams a single load worker had open at once before its first stream failed. Peaks
+    /// of different workers need not overlap in time, so they are not added up.
+    pub max_concurrent_streams_per_worker: usize,
+}
+
+impl SseStats {
+    /// Gaps between consecutive events, which the jitter is averaged over.
+    fn gaps(&self) -> u64 {
+        self.events.saturating_sub(self.streams as u64)
+    }
+
+    pub fn merge(&self, other: &SseStats) -> SseStats {
+        let weighted_mean = |a: f64, a_weight: f64, b: f64, b_weight: f64| {
+            if a_weight + b_weight == 0.0 {
+                0.0
+            } else {
+                (a * a_weight + b * b_weight) / (a_weight + b_weight)
+            }
+        };
+        SseStats {
+            streams: self.streams + other.streams,
+            events: self.events + other.events,
+            time_to_first_event_mean_ms: weighted_mean(
+                self.time_to_first_event_mean_ms,
+                self.streams as f64,
+                other.time_to_first_event_mean_ms,
+                other.streams as f64,
+            ),
+            time_to_first_event_max_ms: self
+                .time_to_first_event_max_ms
+                .max(other.time_to_first_event_max_ms),
+            jitter_mean_ms: weighted_mean(
+                self.jitter_mean_ms,
+                self.gaps() as f64,
+                other.jitter_mean_ms,
+                other.gaps() as f64,
+            ),
+            jitter_max_ms: self.jitter_max_ms.max(other.jitter_max_ms),
+            max_concurrent_streams_per_worker: self
+                .max_concurrent_streams_per_worker
+                .max(other.max_concurrent_streams_per_worker),
+        }
+    }
+}
+
+/// One `id` and `data` pair of the event stream.
+#[derive(Debug, PartialEq)]
+struct SseEvent {
+    id: Option<String>,
+    data: String,
+}
+
+struct SseBenchmark {
+    url: String,
+    template: RequestTemplate,
+    sse_options: SseOptions,
+    stats: Mutex<SseStats>,
+    open_streams: AtomicUsize,
+    peak_streams: AtomicUsize,
+    failed: AtomicBool,
+}
+
+impl SseBenchmark {
+    /// Reads the stream to its end, checking that every event arrives in order.
+    async fn read_stream(
+        &self,
+        start: Instant,
+        mut response: Response,
+    ) -> Result<Result<SseStats, String>, BenchmarkError> {
+        let mut buffer = String::new();
+        let mut arrivals = Vec::with_capacity(self.sse_options.events);
+        while let Some(chunk) = response.chunk().await? {
+            let arrived = Instant::now();
+            buffer.push_str(&String::from_utf8_lossy(&chunk).replace('\r', ""));
+            while let Some(end) = buffer.find("\n\n") {
+                let block: String = buffer.drain(..end + 2).collect();
+                let Some(event) = parse_event(&block) else {
+                    continue;
+                };
+                let sequence = arrivals.len();
+                if sequence >= self.sse_options.events {
+                    return Ok(Err(format!(
+                        "Expected {} events found more",
+                        self.sse_options.events
+                    )));
+                }
+                let expected = reference_event(sequence);
+                if event != expected {
+                    return Ok(Err(format!("Expected event {expected:?} found {event:?}")));
+                }
+                arrivals.push(arrived);
+            }
+        }
+        if arrivals.len() != self.sse_options.events {
+            return Ok(Err(format!(
+                "Expected {} events found {}",
+                self.sse_options.events,
+                arrivals.len()
+            )));
+        }
+
+        let interval = self.sse_options.interval_ms as f64;
+        let jitters: Vec<f64> = arrivals
+            .windows(2)
+            .map(|pair| ((pair[1] - pair[0]).as_secs_f64() * 1000.0 - interval).abs())
+            .collect();
+        let time_to_first_event = arrivals
+            .first()
+            .map(|first| (*first - start).as_secs_f64() * 1000.0)
+            .unwrap_or_default();
+        Ok(Ok(SseStats {
+            streams: 1,
+            events: arrivals.len() as u64,
+            time_to_first_event_mean_ms: time_to_first_event,
+            time_to_first_event_max_ms: time_to_first_event,
+            jitter_mean_ms: if jitters.is_empty() {
+                0.0
+            } else {
+                jitters.iter().sum::<f64>() / jitters.len() as f64
+            },
+            jitter_max_ms: jitters.iter().copied().fold(0.0, f64::max),
+            max_concurrent_streams_per_worker: 0,
+        }))
+    }
+}
+
+#[async_trait]
+impl Benchmark for SseBenchmark {
+    async fn make_request(
+        &self,
+        client: Client,
+        _iteration: usize,
+    ) -> Result<Response, BenchmarkError> {
+        let response = client.get(&self.url).send().await?;
+        Ok(response)
+    }
+
+    async fn check_response(
+        &self,
+        _iteration: usize,
+        start: Instant,
+        response: Response,
+    ) -> Result<BenchmarkResult, BenchmarkError> {
+        if response.status() != StatusCode::OK {
+            self.failed.store(true, Ordering::Relaxed);
+            return Ok(BenchmarkResult::InvalidStatusCode(
+                response.status().as_u16(),
+            ));
+        }
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        if !content_type.starts_with("text/event-stream") {
+            self.failed.store(true, Ordering::Relaxed);
+            return Ok(BenchmarkResult::InvalidResponse(format!(
+                "Expected content type \"text/event-stream\" found \"{content_type}\""
+            )));
+        }
+
+        let open = self.open_streams.fetch_add(1, Ordering::Relaxed) + 1;
+        if !self.failed.load(Ordering::Relaxed) {
+            self.peak_streams.fetch_max(open, Ordering::Relaxed);
+        }
+        let stream = self.read_stream(start, response).await;
+        self.open_streams.fetch_sub(1, Ordering::Relaxed);
+
+        let stream_stats = match stream {
+            Ok(Ok(stream_stats)) => stream_stats,
+            Ok(Err(reason)) => {
+                self.failed.store(true, Ordering::Relaxed);
+                return Ok(BenchmarkResult::InvalidResponse(reason));
+            }
+            Err(err) => {
+                self.failed.store(true, Ordering::Relaxed);
+                return Err(err);
+            }
+        };
+        if let Ok(mut stats) = self.stats.lock() {
+            *stats = stats.merge(&stream_stats);
+        }
+
+        Ok(BenchmarkResult::Ok(Box::new(BenchmarkOkResult {
+            time: start.elapsed(),
+            iterations: 1,
+            ..Default::default()
+        })))
+    }
+
+    fn request_template(
+        &self,
+        _iteration: usize,
+    ) -> Result<Cow<'_, RequestTemplate>, BenchmarkError> {
+        Ok(Cow::Borrowed(&self.template))
+    }
+}
+
+/// Holds event streams open, each request lasting until its last event, and records their timing.
+pub async fn benchmark_sse(
+    options: RunOptions,
+    sse_options: SseOptions,
+) -> Result<BenchmarkResult, BenchmarkError> {
+    info!("benchmark sse {sse_options:?} {options:?}");
+    let url = options.url(&format!(
+        "/benchmark/sse?events={}&interval_ms={}",
+        sse_options.events, sse_options.interval_ms
+    ));
+    let benchmark = Arc::new(SseBenchmark {
+        template: RequestTemplate::get(&url)?,
+        url,
+        sse_options,
+        stats: Mutex::new(SseStats::default()),
+        open_streams: AtomicUsize::new(0),
+        peak_streams: AtomicUsize::new(0),
+        failed: AtomicBool::new(false),
+    });
+
+    let mut result = run_requests(options, benchmark.clone()).await?;
+    if let (BenchmarkResult::Ok(result), Ok(stats)) = (&mut result, benchmark.stats.lock()) {
+        result.sse = Some(SseStats {
+            max_concurrent_streams_per_worker: benchmark.peak_streams.load(Ordering::Relaxed),
+            ..*stats
+        });
+    }
+    Ok(result)
+}
+
+/// Event `sequence` of a stream as a correct webserver sends it.
+fn reference_event(sequence: usize) -> SseEvent {
+    SseEvent {
+        id: Some(sequence.to_string()),
+        data: sequence.to_string(),
+    }
+}
+
+/// The event stream of `events` events a correct webserver sends, for the null server.
+pub fn reference_events(events: usize) -> Vec<String> {
+    (0..events)
+        .map(|sequence| {
+            let event = reference_event(sequence);
+            format!(
+                "id: {}\ndata: {}\n\n",
+                event.id.unwrap_or_default(),
+                event.data
+            )
+        })
+        .collect()
+}
+
+/// Fields of one blank line terminated block, `None` for blocks without data such as comments
+/// sent to keep the connection alive.
+fn parse_event(block: &str) -> Option<SseEvent> {
+    let mut id = None;
+    let mut data: Option<String> = None;
+    for line in block.lines() {
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "id" => id = Some(value.to_string()),
+            "data" => match &mut data {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(value);
+                }
+                None => data = Some(value.to_string()),
+            },
+            _ => {}
+        }
+    }
+    Some(SseEvent { id, data: data? })
+}
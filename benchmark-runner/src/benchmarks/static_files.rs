@@ -0,0 +1,210 @@
+use std::{
+    borrow::Cow,
+    fs::{self, File},
+    io::BufReader,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use log::info;
+use rand::{Rng, RngCore, SeedableRng, rngs::StdRng};
+use reqwest::{Client, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::time::Instant;
+
+use crate::{
+    Benchmark, BenchmarkError, BenchmarkOkResult, BenchmarkResult, RunOptions,
+    hyper_engine::RequestTemplate, run_requests,
+};
+
+pub const STATIC_DIR: &str = "/assets/static";
+
+/// Checksums of the generated tree, kept outside of it so that it is not served.
+const MANIFEST_PATH: &str = "/assets/static-manifest.json";
+
+const FILE_COUNT: usize = 4096;
+const DIRECTORY_COUNT: usize = 64;
+
+/// Share of the files in each size range, in bytes, so that most are small and a few are large.
+const SIZE_CLASSES: [(f64, u64, u64); 4] = [
+    (0.60, 128, 4 * 1024),
+    (0.30, 4 * 1024, 32 * 1024),
+    (0.09, 32 * 1024, 256 * 1024),
+    (0.01, 256 * 1024, 1024 * 1024),
+];
+
+/// Exponent of the Zipf distribution the requested paths follow.
+const ZIPF_EXPONENT: f64 = 1.0;
+
+/// Paths drawn up front, iterations cycle through them.
+const SAMPLES: usize = 65536;
+
+/// One file of the generated tree.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct StaticFile {
+    /// Path relative to [`STATIC_DIR`].
+    path: String,
+    length: usize,
+    sha256: String,
+}
+
+struct StaticFilesBenchmark {
+    urls: Vec<String>,
+    templates: Vec<RequestTemplate>,
+    files: Vec<StaticFile>,
+    /// File requested by every iteration, modulo [`SAMPLES`].
+    samples: Vec<usize>,
+}
+
+impl StaticFilesBenchmark {
+    fn file_index(&self, iteration: usize) -> usize {
+        self.samples[iteration % SAMPLES]
+    }
+}
+
+#[async_trait]
+impl Benchmark for StaticFilesBenchmark {
+    async fn make_request(
+        &self,
+        client: Client,
+        iteration: usize,
+    ) -> Result<Response, BenchmarkError> {
+        let response = client
+            .get(&self.urls[self.file_index(iteration)])
+            .send()
+            .await?;
+        Ok(response)
+    }
+
+    async fn check_response(
+        &self,
+        iteration: usize,
+        start: Instant,
+        response: Response,
+    ) -> Result<BenchmarkResult, BenchmarkError> {
+        if response.status() != StatusCode::OK {
+            return Ok(BenchmarkResult::InvalidStatusCode(
+                response.status().as_u16(),
+            ));
+        }
+
+        let file = &self.files[self.file_index(iteration)];
+        let bytes = response.bytes().await?;
+        if bytes.len() != file.length {
+            return Ok(BenchmarkResult::InvalidResponse(format!(
+                "Expected bytes length {} found bytes len {} for {}",
+                file.length,
+                bytes.len(),
+                file.path
+            )));
+        }
+        if format!("{:x}", Sha256::digest(&bytes)) != file.sha256 {
+            return Ok(BenchmarkResult::InvalidResponse(format!(
+                "Bytes data mismatch for {}",
+                file.path
+            )));
+        }
+
+        Ok(BenchmarkResult::Ok(Box::new(BenchmarkOkResult {
+            time: start.elapsed(),
+            iterations: 1,
+            ..Default::default()
+        })))
+    }
+
+    fn request_template(
+        &self,
+        iteration: usize,
+    ) -> Result<Cow<'_, RequestTemplate>, BenchmarkError> {
+        Ok(Cow::Borrowed(&self.templates[self.file_index(iteration)]))
+    }
+}
+
+pub async fn benchmark_static_files(
+    options: RunOptions,
+) -> Result<BenchmarkResult, BenchmarkError> {
+    info!("benchmark static files {options:?}");
+
+    let files: Vec<StaticFile> =
+        serde_json::from_reader(BufReader::new(File::open(MANIFEST_PATH)?))?;
+    let mut urls = Vec::with_capacity(files.len());
+    let mut templates = Vec::with_capacity(files.len());
+    for file in &files {
+        let url = options.url(&format!("/benchmark/static/{}", file.path));
+        templates.push(RequestTemplate::get(&url)?);
+        urls.push(url);
+    }
+
+    let benchmark = StaticFilesBenchmark {
+        urls,
+        templates,
+        samples: zipf_samples(files.len()),
+        files,
+    };
+    run_requests(options, Arc::new(benchmark)).await
+}
+
+/// File indexes drawn from a Zipf distribution over `count` files, the first being the most
+/// popular.
+fn zipf_samples(count: usize) -> Vec<usize> {
+    let mut cumulative = Vec::with_capacity(count);
+    let mut total = 0.0;
+    for rank in 1..=count {
+        total += 1.0 / (rank as f64).powf(ZIPF_EXPONENT);
+        cumulative.push(total);
+    }
+
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..SAMPLES)
+        .map(|_| {
+            let target = rng.random::<f64>() * total;
+            cumulative
+                .partition_point(|weight| *weight < target)
+                .min(count - 1)
+        })
+        .collect()
+}
+
+/// Writes a tree of [`FILE_COUNT`] files of seeded random bytes and mixed sizes into the assets
+/// volume, plus the manifest with their checksums. The tree is identical on every run, so it is
+/// kept when the manifest from an earlier run is there.
+pub fn generate_static_files() -> Result<(), BenchmarkError> {
+    if fs::metadata(MANIFEST_PATH).is_ok() {
+        return Ok(());
+    }
+
+    info!("generating {FILE_COUNT} static files in {STATIC_DIR}");
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut files = Vec::with_capacity(FILE_COUNT);
+    for index in 0..FILE_COUNT {
+        let directory = format!("d{:02}", index % DIRECTORY_COUNT);
+        fs::create_dir_all(PathBuf::from(STATIC_DIR).join(&directory))?;
+
+        let mut content = vec![0u8; file_size(&mut rng) as usize];
+        rng.fill_bytes(&mut content);
+        let path = format!("{directory}/f{index:04}.bin");
+        fs::write(PathBuf::from(STATIC_DIR).join(&path), &content)?;
+        files.push(StaticFile {
+            path,
+            length: content.len(),
+            sha256: format!("{:x}", Sha256::digest(&content)),
+        });
+    }
+    fs::write(MANIFEST_PATH, serde_json::to_vec(&files)?)?;
+    Ok(())
+}
+
+fn file_size(rng: &mut StdRng) -> u64 {
+    let mut class = rng.random::<f64>();
+    for (share, min, max) in SIZE_CLASSES {
+        if class < share {
+            return rng.random_range(min..max);
+        }
+        class -= share;
+    }
+    let (_, min, max) = SIZE_CLASSES[SIZE_CLASSES.len() - 1];
+    rng.random_range(min..max)
+}
@@ -0,0 +1,200 @@
+use std::{borrow::Cow, fs, sync::Arc};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use log::info;
+use rand::{RngCore, SeedableRng, rngs::StdRng};
+use reqwest::{Client, Response, StatusCode, header::CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::time::Instant;
+
+use crate::{
+    Benchmark, BenchmarkError, BenchmarkOkResult, BenchmarkResult, RunOptions,
+    hyper_engine::RequestTemplate, run_requests,
+};
+
+const MULTIPART_BOUNDARY: &str = "webserver-benchmarks-upload-boundary";
+
+/// What the upload benchmark sends and how.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadOptions {
+    pub payload: UploadPayload,
+    pub encoding: UploadEncoding,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "type"
+)]
+pub enum UploadPayload {
+    /// The PNG served by the download benchmark.
+    Asset,
+    /// Seeded random bytes.
+    Generated { size_bytes: usize },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum UploadEncoding {
+    /// The payload is the request body, sent as `application/octet-stream`.
+    Raw,
+    /// The payload is the `file` field of a `multipart/form-data` body.
+    Multipart,
+}
+
+/// Length and hex encoded SHA-256 of the received payload, as returned by the webserver.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct UploadResponse {
+    length: usize,
+    sha256: String,
+}
+
+impl UploadResponse {
+    fn of(payload: &[u8]) -> Self {
+        Self {
+            length: payload.len(),
+            sha256: format!("{:x}", Sha256::digest(payload)),
+        }
+    }
+}
+
+struct UploadBinaryBenchmark {
+    url: String,
+    template: RequestTemplate,
+    content_type: String,
+    body: Bytes,
+    expected: UploadResponse,
+}
+
+#[async_trait]
+impl Benchmark for UploadBinaryBenchmark {
+    async fn make_request(
+        &self,
+        client: Client,
+        _iteration: usize,
+    ) -> Result<Response, BenchmarkError> {
+        let response = client
+            .post(&self.url)
+            .header(CONTENT_TYPE, &self.content_type)
+            .body(self.body.clone())
+            .send()
+            .await?;
+        Ok(response)
+    }
+
+    async fn check_response(
+        &self,
+        _iteration: usize,
+        start: Instant,
+        response: Response,
+    ) -> Result<BenchmarkResult, BenchmarkError> {
+        if response.status() != StatusCode::OK {
+            return Ok(BenchmarkResult::InvalidStatusCode(
+                response.status().as_u16(),
+            ));
+        }
+        let found: UploadResponse = match response.json().await {
+            Ok(json) => json,
+            Err(err) => {
+                return Ok(BenchmarkResult::InvalidResponse(format!(
+                    "Invalid JSON: {err}"
+                )));
+            }
+        };
+        if found != self.expected {
+            return Ok(BenchmarkResult::InvalidResponse(format!(
+                "Expected length {} and SHA-256 {} found length {} and SHA-256 {}",
+                self.expected.length, self.expected.sha256, found.length, found.sha256
+            )));
+        }
+
+        Ok(BenchmarkResult::Ok(Box::new(BenchmarkOkResult {
+            time: start.elapsed(),
+            iterations: 1,
+            ..Default::default()
+        })))
+    }
+
+    fn request_template(
+        &self,
+        _iteration: usize,
+    ) -> Result<Cow<'_, RequestTemplate>, BenchmarkError> {
+        Ok(Cow::Borrowed(&self.template))
+    }
+}
+
+pub async fn benchmark_upload_binary(
+    options: RunOptions,
+    upload_options: UploadOptions,
+) -> Result<BenchmarkResult, BenchmarkError> {
+    info!("benchmark upload binary {upload_options:?} {options:?}");
+
+    let payload = match upload_options.payload {
+        UploadPayload::Asset => fs::read("/assets/download-binary.png")?,
+        UploadPayload::Generated { size_bytes } => {
+            let mut payload = vec![0u8; size_bytes];
+            StdRng::seed_from_u64(0).fill_bytes(&mut payload);
+            payload
+        }
+    };
+    let expected = UploadResponse::of(&payload);
+    let (content_type, body) = match upload_options.encoding {
+        UploadEncoding::Raw => ("application/octet-stream".to_string(), payload),
+        UploadEncoding::Multipart => (
+            format!("multipart/form-data; boundary={MULTIPART_BOUNDARY}"),
+            multipart_body(&payload),
+        ),
+    };
+    let body = Bytes::from(body);
+
+    let url = options.url("/benchmark/upload-binary");
+    let benchmark = UploadBinaryBenchmark {
+        template: RequestTemplate::post(&url, &content_type, body.clone())?,
+        url,
+        content_type,
+        body,
+        expected,
+    };
+    run_requests(options, Arc::new(benchmark)).await
+}
+
+fn multipart_body(payload: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(payload.len() + 256);
+    body.extend_from_slice(
+        format!(
+            "--{MULTIPART_BOUNDARY}\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"upload.bin\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n"
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(payload);
+    body.extend_from_slice(format!("\r\n--{MULTIPART_BOUNDARY}--\r\n").as_bytes());
+    body
+}
+
+/// Response body a correct webserver returns for a request of `content_type`, used by the
+/// calibration null server. Multipart bodies are expected in the shape this benchmark sends.
+pub fn reference_response(content_type: &str, body: &[u8]) -> Result<Vec<u8>, serde_json::Error> {
+    let payload = match content_type.strip_prefix("multipart/form-data; boundary=") {
+        Some(boundary) => multipart_file(boundary, body).unwrap_or_default(),
+        None => body,
+    };
+    serde_json::to_vec(&UploadResponse::of(payload))
+}
+
+/// Content of the only part of a multipart body, ending at the last delimiter so that the payload
+/// is not scanned.
+fn multipart_file<'a>(boundary: &str, body: &'a [u8]) -> Option<&'a [u8]> {
+    let start = body.windows(4).position(|window| window == b"\r\n\r\n")? + 4;
+    let delimiter = format!("\r\n--{boundary}");
+    let end = body
+        .windows(delimiter.len())
+        .rposition(|window| window == delimiter.as_bytes())?;
+    body.get(start..end)
+}
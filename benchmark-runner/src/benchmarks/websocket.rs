@@ -0,0 +1,430 @@
+use std::{sync::Arc, time::Duration};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::{
+    SinkExt, StreamExt,
+    future::{join_all, try_join_all},
+};
+use log::info;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::mpsc,
+    time::{Instant, sleep},
+};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    BenchmarkError, BenchmarkOkResult, BenchmarkResult, RequestOutcome, RunOptions,
+    summarize_requests,
+    time_series::{LiveHistograms, RequestSample},
+    websocket::{self, WebSocket, WebSocketError},
+};
+
+/// Bytes at the start of every message holding its connection and sequence number.
+const HEADER_BYTES: usize = 16;
+
+/// How the connections of a WebSocket run exchange messages.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum WebSocketMode {
+    /// Every connection sends a message and waits for its echo before sending the next, each
+    /// round trip being one latency sample.
+    Echo,
+    /// Every connection sends its messages without waiting while reading the echoes, for the
+    /// messages per second a single connection carries.
+    Stream,
+    /// Every connection joins one room and the first publishes messages one at a time, each
+    /// delivery being one latency sample from publishing to receipt.
+    Broadcast,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSocketOptions {
+    pub mode: WebSocketMode,
+    pub connections: usize,
+    /// Messages sent per connection, or published in total for broadcasts.
+    pub messages: usize,
+    pub message_bytes: usize,
+}
+
+impl WebSocketOptions {
+    /// Messages received in a run, which its load profile counts as iterations.
+    pub fn iterations(&self) -> usize {
+        self.connections * self.messages
+    }
+}
+
+/// Messages received over the connections of a WebSocket run.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSocketStats {
+    pub connections: usize,
+    pub messages: u64,
+    /// Mean over connections of the messages each received per second while it was active.
+    pub messages_per_sec_per_connection: f64,
+}
+
+impl WebSocketStats {
+    pub fn merge(&self, other: &WebSocketStats) -> WebSocketStats {
+        let connections = self.connections + other.connections;
+        let rate_sum = self.messages_per_sec_per_connection * self.connections as f64
+            + other.messages_per_sec_per_connection * other.connections as f64;
+        WebSocketStats {
+            connections,
+            messages: self.messages + other.messages,
+            messages_per_sec_per_connection: if connections == 0 {
+                0.0
+            } else {
+                rate_sum / connections as f64
+            },
+        }
+    }
+}
+
+/// Outcomes of one connection along with the time it spent receiving them.
+struct ConnectionRun {
+    outcomes: Vec<RequestOutcome>,
+    active: Duration,
+}
+
+/// Opens the connections up front, so that handshakes are not measured, and exchanges messages
+/// on them. Spread over load workers, each worker takes its share of the connections, and
+/// broadcasts run in a room per worker.
+pub async fn benchmark_websocket(
+    options: RunOptions,
+    websocket_options: WebSocketOptions,
+) -> Result<BenchmarkResult, BenchmarkError> {
+    info!("benchmark websocket {websocket_options:?} {options:?}");
+
+    let path = match websocket_options.mode {
+        WebSocketMode::Broadcast => format!("/benchmark/ws?room={:016x}", rand::random::<u64>()),
+        WebSocketMode::Echo | WebSocketMode::Stream => "/benchmark/ws".to_string(),
+    };
+    let connections: Vec<usize> = (0..websocket_options.connections)
+        .filter(|connection| {
+            options
+                .shard
+                .is_none_or(|shard| shard.contains(*connection))
+        })
+        .collect();
+    let sockets = match try_join_all(
+        connections
+            .iter()
+            .map(|_| websocket::connect(&options, &path)),
+    )
+    .await
+    {
+        Ok(sockets) => sockets,
+        Err(WebSocketError::Rejected(status)) => {
+            return Ok(BenchmarkResult::InvalidStatusCode(status));
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    if let Some(shard) = options.shard {
+        sleep(shard.until_start()).await;
+    }
+
+    let start = Instant::now();
+    let runs = match websocket_options.mode {
+        WebSocketMode::Echo => {
+            let runs = connections
+                .into_iter()
+                .zip(sockets)
+                .map(|(connection, socket)| {
+                    tokio::spawn(run_echo(
+                        start,
+                        connection,
+                        socket,
+                        websocket_options,
+                        options.live_histograms.clone(),
+                    ))
+                });
+            join_all(runs).await
+        }
+        WebSocketMode::Stream => {
+            let runs = connections
+                .into_iter()
+                .zip(sockets)
+                .map(|(connection, socket)| {
+                    tokio::spawn(run_stream(
+                        start,
+                        connection,
+                        socket,
+                        websocket_options,
+                        options.live_histograms.clone(),
+                    ))
+                });
+            join_all(runs).await
+        }
+        WebSocketMode::Broadcast => {
+            let run = run_broadcast(
+                start,
+                sockets,
+                websocket_options,
+                options.live_histograms.clone(),
+            );
+            vec![Ok(run.await)]
+        }
+    };
+    let time = start.elapsed();
+
+    let mut outcomes = vec![];
+    let mut rate_sum = 0.0;
+    let mut rates = 0;
+    for run in runs {
+        let run = match run {
+            Ok(Ok(run)) => run,
+            Ok(Err(err)) => {
+                return Ok(BenchmarkResult::InvalidResponse(format!(
+                    "one or more connections failed: {err}"
+                )));
+            }
+            Err(err) => {
+                return Ok(BenchmarkResult::InvalidResponse(format!(
+                    "one or more connections failed: {err}"
+                )));
+            }
+        };
+        if !run.active.is_zero() {
+            rate_sum += run.outcomes.len() as f64 / run.active.as_secs_f64();
+            rates += 1;
+        }
+        outcomes.extend(run.outcomes);
+    }
+    let stats = match websocket_options.mode {
+        // every connection receives every published message
+        WebSocketMode::Broadcast => {
+            let connections = outcomes.len() / websocket_options.messages.max(1);
+            WebSocketStats {
+                connections,
+                messages: outcomes.len() as u64,
+                messages_per_sec_per_connection: if time.is_zero() {
+                    0.0
+                } else {
+                    websocket_options.messages as f64 / time.as_secs_f64()
+                },
+            }
+        }
+        WebSocketMode::Echo | WebSocketMode::Stream => WebSocketStats {
+            connections: rates,
+            messages: outcomes.len() as u64,
+            messages_per_sec_per_connection: if rates == 0 {
+                0.0
+            } else {
+                rate_sum / rates as f64
+            },
+        },
+    };
+
+    let mut result = summarize_requests(options, time, outcomes);
+    if let BenchmarkResult::Ok(result) = &mut result {
+        result.websocket = Some(stats);
+    }
+    Ok(result)
+}
+
+async fn run_echo(
+    start: Instant,
+    connection: usize,
+    mut socket: WebSocket,
+    websocket_options: WebSocketOptions,
+    live_histograms: Option<Arc<LiveHistograms>>,
+) -> Result<ConnectionRun, WebSocketError> {
+    let mut outcomes = Vec::with_capacity(websocket_options.messages);
+    let first_sent = Instant::now();
+    for sequence in 0..websocket_options.messages {
+        let payload = message(connection, sequence, websocket_options.message_bytes);
+        let sent = Instant::now();
+        websocket::send(&mut socket, payload.clone()).await?;
+        let received = websocket::receive(&mut socket).await?;
+        let outcome = outcome(start, sent, &payload, &received);
+        let ok = outcome.0.ok;
+        if let Some(live_histograms) = &live_histograms {
+            live_histograms.record(&outcome.0);
+        }
+        outcomes.push(outcome);
+        if !ok {
+            break;
+        }
+    }
+    let active = first_sent.elapsed();
+    let _ = socket.close(None).await;
+    Ok(ConnectionRun { outcomes, active })
+}
+
+async fn run_stream(
+    start: Instant,
+    connection: usize,
+    socket: WebSocket,
+    websocket_options: WebSocketOptions,
+    live_histograms: Option<Arc<LiveHistograms>>,
+) -> Result<ConnectionRun, WebSocketError> {
+    let (mut sink, mut stream) = socket.split();
+    let (sent_tx, mut sent_rx) = mpsc::unbounded_channel();
+
+    let first_sent = Instant::now();
+    let writer = tokio::spawn(async move {
+        for sequence in 0..websocket_options.messages {
+            let payload = message(connection, sequence, websocket_options.message_bytes);
+            let _ = sent_tx.send(Instant::now());
+            // flushed when the write buffer fills and at the end rather than per message
+            sink.feed(Message::Binary(payload)).await?;
+        }
+        sink.flush().await?;
+        Ok::<_, WebSocketError>(sink)
+    });
+
+    let mut outcomes = Vec::with_capacity(websocket_options.messages);
+    let mut failure = None;
+    for sequence in 0..websocket_options.messages {
+        let received = match websocket::receive(&mut stream).await {
+            Ok(received) => received,
+            Err(err) => {
+                failure = Some(err);
+                break;
+            }
+        };
+        let Some(sent) = sent_rx.recv().await else {
+            failure = Some(WebSocketError::Closed);
+            break;
+        };
+        let payload = message(connection, sequence, websocket_options.message_bytes);
+        let outcome = outcome(start, sent, &payload, &received);
+        let ok = outcome.0.ok;
+        if let Some(live_histograms) = &live_histograms {
+            live_histograms.record(&outcome.0);
+        }
+        outcomes.push(outcome);
+        if !ok {
+            break;
+        }
+    }
+    let active = first_sent.elapsed();
+
+    // the writer would otherwise block once the echoes it no longer reads fill the buffers
+    if outcomes.len() < websocket_options.messages {
+        writer.abort();
+    }
+    if let Some(err) = failure {
+        return Err(err);
+    }
+    if let Ok(Ok(sink)) = writer.await
+        && let Ok(mut socket) = sink.reunite(stream)
+    {
+        let _ = socket.close(None).await;
+    }
+    Ok(ConnectionRun { outcomes, active })
+}
+
+/// Publishes from the first connection and waits until every connection received a message
+/// before publishing the next, so that each round measures the fan-out alone.
+async fn run_broadcast(
+    start: Instant,
+    sockets: Vec<WebSocket>,
+    websocket_options: WebSocketOptions,
+    live_histograms: Option<Arc<LiveHistograms>>,
+) -> Result<ConnectionRun, WebSocketError> {
+    let subscribers = sockets.len();
+    let (receipts_tx, mut receipts_rx) = mpsc::unbounded_channel();
+    let mut sinks = Vec::with_capacity(subscribers);
+    let mut readers = Vec::with_capacity(subscribers);
+    for socket in sockets {
+        let (sink, mut stream) = socket.split();
+        sinks.push(sink);
+        let receipts_tx = receipts_tx.clone();
+        readers.push(tokio::spawn(async move {
+            for _ in 0..websocket_options.messages {
+                let receipt = websocket::receive(&mut stream)
+                    .await
+                    .map(|received| (Instant::now(), received));
+                let failed = receipt.is_err();
+                if receipts_tx.send(receipt).is_err() || failed {
+                    break;
+                }
+            }
+        }));
+    }
+
+    let mut outcomes = Vec::with_capacity(subscribers * websocket_options.messages);
+    if let Some(publisher) = sinks.first_mut() {
+        'rounds: for sequence in 0..websocket_options.messages {
+            let payload = message(0, sequence, websocket_options.message_bytes);
+            let published = Instant::now();
+            websocket::send(publisher, payload.clone()).await?;
+            for _ in 0..subscribers {
+                let (received_at, received) =
+                    receipts_rx.recv().await.ok_or(WebSocketError::Closed)??;
+                let mut outcome = outcome(start, published, &payload, &received);
+                outcome.0.latency = received_at - published;
+                let ok = outcome.0.ok;
+                if let Some(live_histograms) = &live_histograms {
+                    live_histograms.record(&outcome.0);
+                }
+                outcomes.push(outcome);
+                if !ok {
+                    break 'rounds;
+                }
+            }
+        }
+    }
+    let active = start.elapsed();
+
+    for reader in readers {
+        reader.abort();
+    }
+    for mut sink in sinks {
+        let _ = sink.close().await;
+    }
+    Ok(ConnectionRun { outcomes, active })
+}
+
+fn outcome(start: Instant, sent: Instant, expected: &Bytes, received: &Bytes) -> RequestOutcome {
+    let result = if received == expected {
+        Ok(BenchmarkResult::Ok(Box::new(BenchmarkOkResult {
+            time: sent.elapsed(),
+            iterations: 1,
+            ..Default::default()
+        })))
+    } else {
+        Ok(BenchmarkResult::InvalidResponse(format!(
+            "Expected message {} found {}",
+            describe(expected),
+            describe(received)
+        )))
+    };
+    let sample = RequestSample {
+        completed: start.elapsed(),
+        latency: sent.elapsed(),
+        bytes: received.len() as u64,
+        ok: received == expected,
+    };
+    (sample, result)
+}
+
+/// Message `sequence` of `connection`, its numbers followed by a deterministic filler.
+fn message(connection: usize, sequence: usize, message_bytes: usize) -> Bytes {
+    let mut payload = BytesMut::with_capacity(message_bytes.max(HEADER_BYTES));
+    payload.put_u64(connection as u64);
+    payload.put_u64(sequence as u64);
+    for index in HEADER_BYTES..message_bytes {
+        payload.put_u8(b'a' + (index % 26) as u8);
+    }
+    payload.freeze()
+}
+
+fn describe(payload: &Bytes) -> String {
+    if payload.len() < HEADER_BYTES {
+        return format!("of {} bytes", payload.len());
+    }
+    let number = |range: std::ops::Range<usize>| {
+        u64::from_be_bytes(payload[range].try_into().unwrap_or_default())
+    };
+    format!(
+        "{} of connection {} ({} bytes)",
+        number(8..16),
+        number(0..8),
+        payload.len()
+    )
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use log::{info, warn};
+
+use crate::{
+    BenchmarkError, BenchmarkResult, BenchmarkResults,
+    benchmarks::matrix_multiplication::MatrixMultiplicationOptions,
+    manifest::{Sidecar, WebserverManifest},
+    null_server::start_null_server,
+    scenarios::{BenchmarkKind, Scenario, scenarios},
+};
+
+/// Share of the runner's ceiling above which a webserver result is flagged as possibly runner bound.
+const CEILING_MARGIN: f64 = 0.8;
+
+/// Highest requests/second the runner reached against the null server, by scenario name.
+pub type RunnerCeilings = HashMap<String, f64>;
+
+/// Runs every burst scenario against an in-process null server to find the throughput the runner
+/// itself can generate. Time-varying load profiles are rate limited and have no ceiling.
+pub async fn calibrate() -> Result<RunnerCeilings, BenchmarkError> {
+    // the null server speaks h2c but not TLS, and answers database routes without a database
+    let manifest = WebserverManifest {
+        http2: true,
+        tls: false,
+        sidecars: vec![Sidecar::Postgres],
+    };
+    let scenarios: Vec<Scenario> = scenarios(&manifest)
+        .into_iter()
+        .filter(|scenario| scenario.options.load_profile.is_burst())
+        .collect();
+    let matrix_workloads = matrix_workloads(&scenarios);
+    let null_server =
+        tokio::task::spawn_blocking(move || start_null_server(&matrix_workloads)).await??;
+
+    let mut ceilings = RunnerCeilings::new();
+    for scenario in scenarios {
+        info!("calibrating {}", scenario.name);
+        let options = scenario.options.with_null_server(null_server);
+        match scenario.kind.run(options).await {
+            Ok(BenchmarkResult::Ok(result)) => {
+                let rps = result.rps();
+                info!("runner ceiling for {}: {rps:.0} rps", scenario.name);
+                ceilings.insert(scenario.name, rps);
+            }
+            Ok(result) => warn!("calibration of {} failed: {result:?}", scenario.name),
+            Err(err) => warn!("calibration of {} failed: {err}", scenario.name),
+        }
+    }
+    Ok(ceilings)
+}
+
+/// Matrix shapes and encodings the scenarios send, each with the most iterations any of them runs.
+fn matrix_workloads(scenarios: &[Scenario]) -> Vec<(MatrixMultiplicationOptions, usize)> {
+    let mut workloads: Vec<(MatrixMultiplicationOptions, usize)> = vec![];
+    for scenario in scenarios {
+        let BenchmarkKind::MatrixMultiplication(options) = scenario.kind else {
+            continue;
+        };
+        let iterations = scenario.options.load_profile.iterations();
+        // tolerances only differ in how responses are checked, not in what is sent
+        let same_requests = workloads.iter_mut().find(|(other, _)| {
+            (other.rows, other.inner, other.columns, other.encoding)
+                == (
+                    options.rows,
+                    options.inner,
+                    options.columns,
+                    options.encoding,
+                )
+        });
+        match same_requests {
+            Some((_, most)) => *most = (*most).max(iterations),
+            None => workloads.push((options, iterations)),
+        }
+    }
+    workloads
+}
+
+/// Records the runner ceiling on each result and warns when a result comes close to it.
+pub fn apply_ceilings(webserver: &str, results: &mut BenchmarkResults, ceilings: &RunnerCeilings) {
+    for (name, result) in results.iter_mut() {
+        let (BenchmarkResult::Ok(result), Some(ceiling)) = (result, ceilings.get(name)) else {
+            continue;
+        };
+        result.runner_ceiling_rps = Some(*ceiling);
+        if result.rps() >= ceiling * CEILING_MARGIN {
+            warn!(
+                "{webserver} {name}: {:.0} rps is within {:.0}% of the runner ceiling of {ceiling:.0} rps, the runner may be the bottleneck",
+                result.rps(),
+                (1.0 - CEILING_MARGIN) * 100.0
+            );
+            result.near_runner_ceiling = true;
+        }
+    }
+}
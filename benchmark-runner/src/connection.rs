@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use reqwest::{
+    Client,
+    header::{CONNECTION, HeaderMap, HeaderValue},
+};
+use rustls::ClientConfig;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{BenchmarkError, RunOptions, protocol::Protocol, tls::client_config};
+
+/// How the load engine manages TCP connections to the webserver.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "type"
+)]
+pub enum ConnectionMode {
+    /// Reuse keep-alive connections, capped at `max_connections` open at once when set.
+    KeepAlive {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_connections: Option<usize>,
+    },
+    /// Send `Connection: close` so every request opens a new TCP connection. HTTP/1 only.
+    Close,
+    /// Build a fresh client, and with it a fresh connection pool, for every request.
+    ClientPerRequest,
+}
+
+impl Default for ConnectionMode {
+    fn default() -> Self {
+        ConnectionMode::KeepAlive {
+            max_connections: None,
+        }
+    }
+}
+
+impl ConnectionMode {
+    pub fn client(
+        &self,
+        options: &RunOptions,
+        tls_config: Option<&ClientConfig>,
+    ) -> Result<Client, reqwest::Error> {
+        let mut builder = options.protocol.configure(Client::builder());
+        if let Some(tls_config) = tls_config {
+            builder = builder.use_preconfigured_tls(tls_config.clone());
+        }
+        match self {
+            ConnectionMode::KeepAlive { max_connections } => {
+                if let Some(max_connections) = max_connections {
+                    builder = builder.pool_max_idle_per_host(*max_connections);
+                }
+            }
+            ConnectionMode::Close => {
+                let mut headers = HeaderMap::new();
+                headers.insert(CONNECTION, HeaderValue::from_static("close"));
+                builder = builder.default_headers(headers).pool_max_idle_per_host(0);
+            }
+            ConnectionMode::ClientPerRequest => {}
+        }
+        builder.build()
+    }
+
+    pub fn is_client_per_request(&self) -> bool {
+        matches!(self, ConnectionMode::ClientPerRequest)
+    }
+
+    /// Limits in-flight requests so that no more than `max_connections` connections are opened.
+    pub fn limiter(&self) -> Option<Arc<Semaphore>> {
+        match self {
+            ConnectionMode::KeepAlive {
+                max_connections: Some(max_connections),
+            } => Some(Arc::new(Semaphore::new(*max_connections))),
+            _ => None,
+        }
+    }
+}
+
+/// Clients requests are spread across, each optionally capped in concurrent requests.
+pub struct ClientPool {
+    clients: Vec<(Client, Option<Arc<Semaphore>>)>,
+    options: RunOptions,
+    tls_config: Option<ClientConfig>,
+}
+
+impl ClientPool {
+    pub fn new(options: &RunOptions) -> Result<Self, BenchmarkError> {
+        let connection_mode = options.connection_mode;
+        // shared by every client so that TLS sessions can be resumed across connections
+        let tls_config = match &options.tls {
+            Some(tls) => Some(client_config(tls, options.protocol)?),
+            None => None,
+        };
+        let clients = match options.protocol {
+            Protocol::Http1 => vec![(
+                connection_mode.client(options, tls_config.as_ref())?,
+                connection_mode.limiter(),
+            )],
+            // each client holds a single HTTP/2 connection, so one client per connection
+            Protocol::Http2 {
+                connections,
+                streams_per_connection,
+            } => (0..connections)
+                .map(|_| {
+                    let limiter = Arc::new(Semaphore::new(streams_per_connection));
+                    Ok((
+                        connection_mode.client(options, tls_config.as_ref())?,
+                        Some(limiter),
+                    ))
+                })
+                .collect::<Result<_, reqwest::Error>>()?,
+        };
+        Ok(Self {
+            clients,
+            options: options.clone(),
+            tls_config,
+        })
+    }
+
+    /// Picks the client for `iteration`, waiting for a free connection or stream if limited.
+    pub async fn acquire(
+        &self,
+        iteration: usize,
+    ) -> Result<(Client, Option<OwnedSemaphorePermit>), BenchmarkError> {
+        let (client, limiter) = &self.clients[iteration % self.clients.len()];
+        let permit = match limiter {
+            Some(limiter) => Some(limiter.clone().acquire_owned().await?),
+            None => None,
+        };
+        let client = if self.options.connection_mode.is_client_per_request() {
+            self.options
+                .connection_mode
+                .client(&self.options, self.tls_config.as_ref())?
+        } else {
+            client.clone()
+        };
+        Ok((client, permit))
+    }
+}
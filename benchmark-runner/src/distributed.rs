@@ -0,0 +1,403 @@
+use std::{
+    convert::Infallible,
+    env,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use futures::{future::join_all, stream};
+use http::{
+    Method, Request, Response, StatusCode,
+    header::{CONTENT_TYPE, HeaderValue},
+};
+use http_body_util::{BodyExt, Full, StreamBody, combinators::BoxBody};
+use hyper::{
+    body::{Frame, Incoming},
+    server::conn::http1,
+    service::service_fn,
+};
+use hyper_util::rt::TokioIo;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::{
+    net::{TcpListener, lookup_host},
+    sync::mpsc,
+    time::{Instant, interval_at},
+};
+
+use crate::{
+    BenchmarkError, BenchmarkOkResult, BenchmarkResult, RunOptions,
+    benchmarks::{
+        compression::CompressionStats, delay::sustained_concurrency,
+        matrix_multiplication::FloatError, sse::SseStats, websocket::WebSocketStats,
+    },
+    scenarios::{BenchmarkKind, Scenario},
+    time_series::{LiveHistograms, SecondHistogram, bucket_histograms, merge_histograms},
+};
+
+/// Comma separated `host:port` list of load workers. Every address a host resolves to is a
+/// worker, so a scaled compose service can be listed once by its service name.
+const WORKERS_ENV: &str = "BENCHMARK_WORKERS";
+
+/// Default address a worker listens on for scenarios from the coordinator.
+pub const DEFAULT_WORKER_ADDR: &str = "0.0.0.0:9000";
+
+/// Time between handing out a scenario and its synchronized start, enough for every worker to
+/// build its clients and send its warmup request.
+const START_DELAY: Duration = Duration::from_secs(2);
+
+/// Time a worker waits after a second ended before streaming its histogram.
+const SECOND_GRACE: Duration = Duration::from_millis(200);
+
+type Body = BoxBody<Bytes, Infallible>;
+
+#[derive(Error, Debug)]
+pub enum DistributedError {
+    #[error("IO: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid worker address {0}")]
+    InvalidAddress(String),
+
+    #[error("Worker {worker}: {message}")]
+    Worker { worker: SocketAddr, message: String },
+}
+
+/// Slice of a run's iterations handled by one of several load workers, along with the wall
+/// clock time all workers start at.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+    pub start_at_unix_ms: u64,
+}
+
+impl Shard {
+    pub fn contains(&self, iteration: usize) -> bool {
+        iteration % self.count == self.index
+    }
+
+    /// Time left until the synchronized start, zero once it has passed.
+    pub fn until_start(&self) -> Duration {
+        let start = UNIX_EPOCH + Duration::from_millis(self.start_at_unix_ms);
+        start
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO)
+    }
+}
+
+/// Scenario sent from the coordinator to a worker.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct WorkerJob {
+    kind: BenchmarkKind,
+    options: RunOptions,
+}
+
+/// One line of the newline delimited JSON a worker streams back for a job.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "type"
+)]
+enum WorkerMessage {
+    /// Requests completed within one second of the shard's run, sent once the second is over.
+    /// Requests completing into a second after it was sent follow in another line for it.
+    Second(SecondHistogram),
+    /// Sent after the last second once the shard completed.
+    Done {
+        time_ms: u64,
+        iterations: usize,
+        errors: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_float_error: Option<FloatError>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        compression: Option<CompressionStats>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sse: Option<SseStats>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        websocket: Option<WebSocketStats>,
+    },
+    /// The shard failed, the coordinator reports the run as invalid.
+    Failed { reason: String },
+}
+
+/// Workers configured through `BENCHMARK_WORKERS`, none when load is generated in process.
+pub async fn worker_addresses() -> Result<Option<Vec<SocketAddr>>, DistributedError> {
+    let Ok(workers) = env::var(WORKERS_ENV) else {
+        return Ok(None);
+    };
+
+    let mut addresses = vec![];
+    for worker in workers.split(',').map(str::trim).filter(|w| !w.is_empty()) {
+        let resolved: Vec<_> = lookup_host(worker).await?.collect();
+        if resolved.is_empty() {
+            return Err(DistributedError::InvalidAddress(worker.to_string()));
+        }
+        addresses.extend(resolved);
+    }
+    addresses.sort();
+    addresses.dedup();
+    if addresses.is_empty() {
+        return Ok(None);
+    }
+    info!("generating load from workers {addresses:?}");
+    Ok(Some(addresses))
+}
+
+/// Runs `scenario` on every worker, each taking an equal shard of its iterations from the same
+/// start time, and merges their histograms into one result.
+pub async fn run_distributed(
+    scenario: &Scenario,
+    workers: &[SocketAddr],
+) -> Result<BenchmarkResult, BenchmarkError> {
+    let start_at = SystemTime::now() + START_DELAY;
+    let start_at_unix_ms = start_at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+
+    let client = reqwest::Client::new();
+    let runs = workers.iter().enumerate().map(|(index, worker)| {
+        let mut options = scenario.options.clone();
+        options.shard = Some(Shard {
+            index,
+            count: workers.len(),
+            start_at_unix_ms,
+        });
+        let job = WorkerJob {
+            kind: scenario.kind,
+            options,
+        };
+        run_on_worker(&client, *worker, job)
+    });
+
+    let mut histograms = vec![];
+    let mut time = Duration::ZERO;
+    let mut iterations = 0;
+    let mut errors = 0;
+    let mut max_float_error: Option<FloatError> = None;
+    let mut compression: Option<CompressionStats> = None;
+    let mut sse: Option<SseStats> = None;
+    let mut websocket: Option<WebSocketStats> = None;
+    for (worker, messages) in workers.iter().zip(join_all(runs).await) {
+        for message in messages? {
+            match message {
+                WorkerMessage::Second(second) => merge_histograms(&mut histograms, &[second]),
+                WorkerMessage::Done {
+                    time_ms,
+                    iterations: worker_iterations,
+                    errors: worker_errors,
+                    max_float_error: worker_max_float_error,
+                    compression: worker_compression,
+                    sse: worker_sse,
+                    websocket: worker_websocket,
+                } => {
+                    time = time.max(Duration::from_millis(time_ms));
+                    iterations += worker_iterations;
+                    errors += worker_errors;
+                    if let Some(worker_max_float_error) = worker_max_float_error {
+                        max_float_error = Some(
+                            max_float_error
+                                .unwrap_or_default()
+                                .max(worker_max_float_error),
+                        );
+                    }
+                    if let Some(worker_compression) = worker_compression {
+                        compression =
+                            Some(compression.unwrap_or_default().merge(&worker_compression));
+                    }
+                    if let Some(worker_sse) = worker_sse {
+                        sse = Some(sse.unwrap_or_default().merge(&worker_sse));
+                    }
+                    if let Some(worker_websocket) = worker_websocket {
+                        websocket = Some(websocket.unwrap_or_default().merge(&worker_websocket));
+                    }
+                }
+                WorkerMessage::Failed { reason } => {
+                    return Ok(BenchmarkResult::InvalidResponse(format!(
+                        "worker {worker}: {reason}"
+                    )));
+                }
+            }
+        }
+    }
+
+    let time_series = bucket_histograms(&histograms);
+    // computed from the merged time series, since every worker only sees its own share of the load
+    let sustained_concurrency = match scenario.kind {
+        BenchmarkKind::Delay(delay_options) if !scenario.options.load_profile.is_burst() => {
+            sustained_concurrency(&time_series, delay_options)
+        }
+        _ => None,
+    };
+    Ok(BenchmarkResult::Ok(Box::new(BenchmarkOkResult {
+        time,
+        iterations,
+        errors,
+        options: Some(scenario.options.clone()),
+        load_workers: Some(workers.len()),
+        max_float_error,
+        compression,
+        sse,
+        websocket,
+        sustained_concurrency,
+        time_series,
+        ..Default::default()
+    })))
+}
+
+async fn run_on_worker(
+    client: &reqwest::Client,
+    worker: SocketAddr,
+    job: WorkerJob,
+) -> Result<Vec<WorkerMessage>, BenchmarkError> {
+    let mut response = client
+        .post(format!("http://{worker}/run"))
+        .json(&job)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(DistributedError::Worker {
+            worker,
+            message: format!("status {}", response.status()),
+        }
+        .into());
+    }
+
+    // parse lines as they arrive instead of buffering the whole stream
+    let mut messages = vec![];
+    let mut pending = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        pending.extend_from_slice(&chunk);
+        while let Some(end) = pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            messages.push(serde_json::from_slice(&line)?);
+        }
+    }
+    Ok(messages)
+}
+
+/// Serves scenarios to a coordinator until the process is stopped.
+pub async fn run_worker(addr: &str) -> Result<(), DistributedError> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("load worker listening on {}", listener.local_addr()?);
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            if let Err(err) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service_fn(handle))
+                .await
+            {
+                warn!("worker connection failed: {err}");
+            }
+        });
+    }
+}
+
+async fn handle(request: Request<Incoming>) -> Result<Response<Body>, Infallible> {
+    if request.method() != Method::POST || request.uri().path() != "/run" {
+        return Ok(response(StatusCode::NOT_FOUND, full(Bytes::new())));
+    }
+    let Ok(body) = request
+        .into_body()
+        .collect()
+        .await
+        .map(|body| body.to_bytes())
+    else {
+        return Ok(response(StatusCode::BAD_REQUEST, full(Bytes::new())));
+    };
+    let Ok(job) = serde_json::from_slice::<WorkerJob>(&body) else {
+        return Ok(response(StatusCode::BAD_REQUEST, full(Bytes::new())));
+    };
+
+    // the job outlives this handler, streaming its lines into the response as it runs
+    let (sender, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(run_job(job, sender));
+    let lines = stream::unfold(receiver, |mut receiver| async move {
+        let line = receiver.recv().await?;
+        Some((Ok(Frame::data(line)), receiver))
+    });
+    Ok(response(StatusCode::OK, StreamBody::new(lines).boxed()))
+}
+
+/// Runs a shard, sending the histogram of every second once it is over and the summary of the
+/// shard last.
+async fn run_job(job: WorkerJob, sender: mpsc::UnboundedSender<Bytes>) {
+    info!("running shard {:?} of {:?}", job.options.shard, job.kind);
+    let send = |message: &WorkerMessage| match serde_json::to_vec(message) {
+        Ok(mut line) => {
+            line.push(b'\n');
+            let _ = sender.send(Bytes::from(line));
+        }
+        Err(err) => warn!("worker message not serialized: {err}"),
+    };
+
+    let live_histograms = Arc::new(LiveHistograms::default());
+    let mut options = job.options;
+    options.live_histograms = Some(live_histograms.clone());
+    // seconds count from the synchronized start, the grace leaves room for requests that
+    // complete right at the end of a second to be recorded
+    let start = Instant::now()
+        + options
+            .shard
+            .map_or(Duration::ZERO, |shard| shard.until_start());
+    let mut ticks = interval_at(
+        start + Duration::from_secs(1) + SECOND_GRACE,
+        Duration::from_secs(1),
+    );
+    let run = job.kind.run(options);
+    tokio::pin!(run);
+    let result = loop {
+        tokio::select! {
+            result = &mut run => break result,
+            _ = ticks.tick() => {
+                let over = start.elapsed().saturating_sub(SECOND_GRACE).as_secs();
+                for second in live_histograms.take_before(over) {
+                    send(&WorkerMessage::Second(second));
+                }
+            }
+        }
+    };
+    for second in live_histograms.take_before(u64::MAX) {
+        send(&WorkerMessage::Second(second));
+    }
+
+    let message = match result {
+        Ok(BenchmarkResult::Ok(result)) => WorkerMessage::Done {
+            time_ms: result.time.as_millis() as u64,
+            iterations: result.iterations,
+            errors: result.errors,
+            max_float_error: result.max_float_error,
+            compression: result.compression,
+            sse: result.sse,
+            websocket: result.websocket,
+        },
+        Ok(result) => WorkerMessage::Failed {
+            reason: format!("{result:?}"),
+        },
+        Err(err) => WorkerMessage::Failed {
+            reason: format!("{err}"),
+        },
+    };
+    send(&message);
+}
+
+fn full(body: Bytes) -> Body {
+    Full::new(body).map_err(|never| match never {}).boxed()
+}
+
+fn response(status: StatusCode, body: Body) -> Response<Body> {
+    let mut response = Response::new(body);
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("application/x-ndjson"),
+    );
+    response
+}
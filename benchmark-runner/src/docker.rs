@@ -1,10 +1,20 @@
 use std::cmp::Reverse;
-use std::process::{Child, Command};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
 
 use log::{debug, info};
 use serde::Deserialize;
 use serde_json::from_str;
 use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+
+use crate::{benchmarks::database::postgres_seed_sql, manifest::Sidecar};
+
+/// Compose project and file of the runner, as mounted into its container, which declare the
+/// sidecars on the network the webservers join.
+const COMPOSE_PROJECT: &str = "benchmark-runner";
+const COMPOSE_FILE: &str = "/app/docker-compose.yml";
+const SIDECAR_HEALTH_TIMEOUT: Duration = Duration::from_secs(60);
 
 #[derive(Error, Debug)]
 pub enum DockerError {
@@ -42,7 +52,12 @@ struct InspectMount {
     destination: String,
 }
 
-pub fn run_webserver(name: &str) -> Result<Child, DockerError> {
+/// Starts the webserver's sidecars, each ready and seeded, then the webserver itself.
+pub async fn run_webserver(name: &str, sidecars: &[Sidecar]) -> Result<Child, DockerError> {
+    for sidecar in sidecars {
+        start_sidecar(*sidecar).await?;
+    }
+
     let mut cmd = Command::new("docker");
     let args = ["compose", "up", "--build", "--remove-orphans"];
     let assets_dir = get_assets_dir()?;
@@ -56,14 +71,172 @@ pub fn run_webserver(name: &str) -> Result<Child, DockerError> {
     Ok(child)
 }
 
-pub fn stop_webserver(name: &str) -> Result<(), DockerError> {
-    let mut cmd = Command::new("docker");
+pub async fn stop_webserver(name: &str, sidecars: &[Sidecar]) -> Result<(), DockerError> {
+    let mut cmd = tokio::process::Command::new("docker");
     let args = ["compose", "stop"];
 
     info!("stopping webserver: {name}");
     cmd.args(args)
         .current_dir(format!("/webservers/{name}"))
-        .output()?;
+        .output()
+        .await?;
+
+    for sidecar in sidecars {
+        stop_sidecar(*sidecar).await?;
+    }
+    Ok(())
+}
+
+/// Id of the container running the webserver's `web` service.
+pub async fn webserver_container(name: &str) -> Result<String, DockerError> {
+    let output = tokio::process::Command::new("docker")
+        .args(["compose", "ps", "--quiet", "web"])
+        .current_dir(format!("/webservers/{name}"))
+        .output()
+        .await?;
+    let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if id.is_empty() {
+        return Err(DockerError::Other(format!(
+            "Could not find the web container of {name}"
+        )));
+    }
+    Ok(id)
+}
+
+/// Memory a container uses, from the `12.5MiB / 7.6GiB` usage `docker stats` reports.
+pub async fn container_memory_bytes(id: &str) -> Result<u64, DockerError> {
+    let output = tokio::process::Command::new("docker")
+        .args(["stats", "--no-stream", "--format", "{{.MemUsage}}", id])
+        .output()
+        .await?;
+    let usage = String::from_utf8_lossy(&output.stdout);
+    parse_memory(&usage)
+        .ok_or_else(|| DockerError::Other(format!("Invalid memory usage \"{}\"", usage.trim())))
+}
+
+fn parse_memory(usage: &str) -> Option<u64> {
+    let used = usage.split('/').next()?.trim();
+    let unit_start = used.find(|c: char| c.is_ascii_alphabetic())?;
+    let (value, unit) = used.split_at(unit_start);
+    let multiplier: u64 = match unit {
+        "B" => 1,
+        "KiB" => 1 << 10,
+        "MiB" => 1 << 20,
+        "GiB" => 1 << 30,
+        "TiB" => 1 << 40,
+        "kB" | "KB" => 1_000,
+        "MB" => 1_000_000,
+        "GB" => 1_000_000_000,
+        "TB" => 1_000_000_000_000,
+        _ => return None,
+    };
+    Some((value.trim().parse::<f64>().ok()? * multiplier as f64) as u64)
+}
+
+/// `docker compose` for the runner's own project, whose file declares every sidecar as a service
+/// under a profile of the same name.
+fn sidecar_compose(sidecar: Sidecar) -> tokio::process::Command {
+    let service = sidecar_service(sidecar);
+    let mut cmd = tokio::process::Command::new("docker");
+    cmd.args([
+        "compose",
+        "--project-name",
+        COMPOSE_PROJECT,
+        "--file",
+        COMPOSE_FILE,
+        "--profile",
+        service,
+    ]);
+    cmd
+}
+
+/// Recreates the sidecar's container, so that every webserver sees the same data, then waits for
+/// its health check and seeds it.
+async fn start_sidecar(sidecar: Sidecar) -> Result<(), DockerError> {
+    let service = sidecar_service(sidecar);
+    info!("starting sidecar: {sidecar:?}");
+    let output = sidecar_compose(sidecar)
+        .args([
+            "up",
+            "--detach",
+            "--force-recreate",
+            "--renew-anon-volumes",
+            "--wait",
+            "--wait-timeout",
+            &SIDECAR_HEALTH_TIMEOUT.as_secs().to_string(),
+            service,
+        ])
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(DockerError::Other(format!(
+            "Could not start sidecar {sidecar:?}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    info!("seeding sidecar: {sidecar:?}");
+    match sidecar {
+        Sidecar::Postgres => {
+            let command = [
+                "psql",
+                "--quiet",
+                "--set",
+                "ON_ERROR_STOP=1",
+                "--username",
+                "benchmark",
+                "--dbname",
+                "benchmark",
+            ];
+            compose_exec_stdin(sidecar, &command, postgres_seed_sql()).await
+        }
+    }
+}
+
+async fn stop_sidecar(sidecar: Sidecar) -> Result<(), DockerError> {
+    debug!("removing sidecar: {sidecar:?}");
+    sidecar_compose(sidecar)
+        .args([
+            "rm",
+            "--force",
+            "--stop",
+            "--volumes",
+            sidecar_service(sidecar),
+        ])
+        .output()
+        .await?;
+    Ok(())
+}
+
+fn sidecar_service(sidecar: Sidecar) -> &'static str {
+    match sidecar {
+        Sidecar::Postgres => "postgres",
+    }
+}
+
+async fn compose_exec_stdin(
+    sidecar: Sidecar,
+    command: &[&str],
+    stdin: String,
+) -> Result<(), DockerError> {
+    let mut child = sidecar_compose(sidecar)
+        .args(["exec", "--no-TTY", sidecar_service(sidecar)])
+        .args(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut child_stdin) = child.stdin.take() {
+        child_stdin.write_all(stdin.as_bytes()).await?;
+    }
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(DockerError::Other(format!(
+            "{} in {sidecar:?} failed: {}",
+            command.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
     Ok(())
 }
 
@@ -0,0 +1,360 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use bytes::Bytes;
+use http::{
+    HeaderMap, HeaderValue, Method, Request, Uri,
+    header::{CONTENT_TYPE, HOST, HeaderName},
+    uri::{Authority, InvalidUri},
+};
+use http_body_util::{BodyExt, Full};
+use hyper::client::conn::{http1, http2};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use reqwest::Response;
+use thiserror::Error;
+use tokio::{
+    net::TcpStream,
+    time::{Instant, sleep_until},
+};
+
+use crate::{
+    Benchmark, BenchmarkError, BenchmarkResult, RequestOutcome, RunOptions,
+    connection::ConnectionMode,
+    protocol::Protocol,
+    scheduled_start, summarize_requests,
+    time_series::{LiveHistograms, RequestSample},
+};
+
+#[derive(Error, Debug)]
+pub enum HyperEngineError {
+    #[error("IO: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Hyper: {0}")]
+    Hyper(#[from] hyper::Error),
+
+    #[error("HTTP: {0}")]
+    Http(#[from] http::Error),
+
+    #[error("Invalid URI: {0}")]
+    InvalidUri(#[from] InvalidUri),
+
+    #[error("Unsupported: {0}")]
+    Unsupported(String),
+}
+
+/// A request built once and cheaply cloned for every iteration that sends it.
+#[derive(Debug, Clone)]
+pub struct RequestTemplate {
+    method: Method,
+    authority: Authority,
+    path: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl RequestTemplate {
+    pub fn get(url: &str) -> Result<Self, HyperEngineError> {
+        Self::new(Method::GET, url, HeaderMap::new(), Bytes::new())
+    }
+
+    pub fn post(url: &str, content_type: &str, body: Bytes) -> Result<Self, HyperEngineError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_str(content_type).map_err(http::Error::from)?,
+        );
+        Self::new(Method::POST, url, headers, body)
+    }
+
+    pub fn with_header(mut self, name: HeaderName, value: &str) -> Result<Self, HyperEngineError> {
+        self.headers.insert(
+            name,
+            HeaderValue::from_str(value).map_err(http::Error::from)?,
+        );
+        Ok(self)
+    }
+
+    fn new(
+        method: Method,
+        url: &str,
+        mut headers: HeaderMap,
+        body: Bytes,
+    ) -> Result<Self, HyperEngineError> {
+        let uri: Uri = url.parse()?;
+        let authority = uri
+            .authority()
+            .cloned()
+            .ok_or_else(|| HyperEngineError::Unsupported(format!("URL without host: {url}")))?;
+        let path = uri
+            .path_and_query()
+            .map_or("/", |path| path.as_str())
+            .parse()?;
+        headers.insert(
+            HOST,
+            HeaderValue::from_str(authority.as_str()).map_err(http::Error::from)?,
+        );
+        Ok(Self {
+            method,
+            authority,
+            path,
+            headers,
+            body,
+        })
+    }
+
+    pub fn authority(&self) -> &Authority {
+        &self.authority
+    }
+
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Appends the request in HTTP/1.1 wire format, as written by the pipelined engine.
+    pub fn encode_http1(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(self.method.as_str().as_bytes());
+        buffer.push(b' ');
+        buffer.extend_from_slice(self.path.to_string().as_bytes());
+        buffer.extend_from_slice(b" HTTP/1.1\r\n");
+        for (name, value) in &self.headers {
+            buffer.extend_from_slice(name.as_str().as_bytes());
+            buffer.extend_from_slice(b": ");
+            buffer.extend_from_slice(value.as_bytes());
+            buffer.extend_from_slice(b"\r\n");
+        }
+        if !self.body.is_empty() {
+            buffer.extend_from_slice(format!("content-length: {}\r\n", self.body.len()).as_bytes());
+        }
+        buffer.extend_from_slice(b"\r\n");
+        buffer.extend_from_slice(&self.body);
+    }
+
+    fn build(&self, protocol: Protocol) -> Result<Request<Full<Bytes>>, HyperEngineError> {
+        // HTTP/2 carries the authority in the request URI instead of a Host header
+        let uri = match protocol {
+            Protocol::Http1 => self.path.clone(),
+            Protocol::Http2 { .. } => Uri::builder()
+                .scheme("http")
+                .authority(self.authority.clone())
+                .path_and_query(self.path.path_and_query().map_or("/", |p| p.as_str()))
+                .build()?,
+        };
+        let mut request = Request::builder().method(self.method.clone()).uri(uri);
+        for (name, value) in &self.headers {
+            if protocol.is_http2() && name == HOST {
+                continue;
+            }
+            request = request.header(name, value);
+        }
+        Ok(request.body(Full::new(self.body.clone()))?)
+    }
+}
+
+enum Connection {
+    Http1 {
+        authority: Authority,
+        sender: http1::SendRequest<Full<Bytes>>,
+    },
+    Http2(http2::SendRequest<Full<Bytes>>),
+}
+
+impl Connection {
+    async fn http1(authority: &Authority) -> Result<Self, HyperEngineError> {
+        let stream = connect(authority).await?;
+        let (sender, connection) = http1::handshake(TokioIo::new(stream)).await?;
+        tokio::spawn(connection);
+        Ok(Connection::Http1 {
+            authority: authority.clone(),
+            sender,
+        })
+    }
+
+    async fn http2(authority: &Authority) -> Result<Self, HyperEngineError> {
+        let stream = connect(authority).await?;
+        let (sender, connection) =
+            http2::handshake(TokioExecutor::new(), TokioIo::new(stream)).await?;
+        tokio::spawn(connection);
+        Ok(Connection::Http2(sender))
+    }
+
+    fn share(&self) -> Option<Self> {
+        match self {
+            Connection::Http1 { .. } => None,
+            Connection::Http2(sender) => Some(Connection::Http2(sender.clone())),
+        }
+    }
+
+    async fn send(
+        &mut self,
+        request: Request<Full<Bytes>>,
+    ) -> Result<http::Response<Bytes>, HyperEngineError> {
+        // the server may close idle connections, reopen them
+        if let Connection::Http1 { authority, sender } = self
+            && sender.ready().await.is_err()
+        {
+            let authority = authority.clone();
+            *self = Connection::http1(&authority).await?;
+        }
+
+        let response = match self {
+            Connection::Http1 { sender, .. } => {
+                sender.ready().await?;
+                sender.send_request(request).await?
+            }
+            Connection::Http2(sender) => {
+                sender.ready().await?;
+                sender.send_request(request).await?
+            }
+        };
+        let (parts, body) = response.into_parts();
+        let body = body.collect().await?.to_bytes();
+        Ok(http::Response::from_parts(parts, body))
+    }
+}
+
+pub async fn connect(authority: &Authority) -> Result<TcpStream, HyperEngineError> {
+    let port = authority.port_u16().unwrap_or(80);
+    let stream = TcpStream::connect((authority.host(), port)).await?;
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
+
+struct WorkerContext {
+    benchmark: Arc<dyn Benchmark>,
+    protocol: Protocol,
+    schedule: Vec<(usize, std::time::Duration)>,
+    next: AtomicUsize,
+    start: Instant,
+    is_burst: bool,
+    live_histograms: Option<Arc<LiveHistograms>>,
+}
+
+/// Runs the load profile through per-worker loops, each worker pulling the next scheduled
+/// iteration and sending it on its own connection, or its own stream for HTTP/2.
+pub async fn run_hyper_requests(
+    options: RunOptions,
+    benchmark: Arc<dyn Benchmark>,
+    workers: usize,
+) -> Result<BenchmarkResult, BenchmarkError> {
+    if options.tls.is_some() {
+        return Err(HyperEngineError::Unsupported("TLS".to_string()).into());
+    }
+    if !matches!(options.connection_mode, ConnectionMode::KeepAlive { .. }) {
+        return Err(HyperEngineError::Unsupported(format!(
+            "connection mode {:?}",
+            options.connection_mode
+        ))
+        .into());
+    }
+
+    let authority = benchmark.request_template(0)?.authority.clone();
+    let mut connections = vec![];
+    match options.protocol {
+        Protocol::Http1 => {
+            for _ in 0..workers {
+                connections.push(Connection::http1(&authority).await?);
+            }
+        }
+        Protocol::Http2 {
+            connections: count,
+            streams_per_connection,
+        } => {
+            for _ in 0..count {
+                let connection = Connection::http2(&authority).await?;
+                for _ in 1..streams_per_connection {
+                    connections.extend(connection.share());
+                }
+                connections.push(connection);
+            }
+        }
+    }
+
+    let schedule = options.schedule();
+    let iterations = schedule.len();
+    let context = Arc::new(WorkerContext {
+        benchmark,
+        protocol: options.protocol,
+        schedule,
+        next: AtomicUsize::new(0),
+        start: Instant::now(),
+        is_burst: options.load_profile.is_burst(),
+        live_histograms: options.live_histograms.clone(),
+    });
+
+    let handles: Vec<_> = connections
+        .into_iter()
+        .map(|connection| tokio::spawn(run_worker(context.clone(), connection)))
+        .collect();
+
+    let mut outcomes = Vec::with_capacity(iterations);
+    for handle in handles {
+        match handle.await {
+            Ok(worker_outcomes) => outcomes.extend(worker_outcomes),
+            Err(err) => {
+                return Ok(BenchmarkResult::InvalidResponse(format!(
+                    "one or more requests failed: {err}"
+                )));
+            }
+        }
+    }
+    let time = context.start.elapsed();
+
+    Ok(summarize_requests(options, time, outcomes))
+}
+
+async fn run_worker(
+    context: Arc<WorkerContext>,
+    mut connection: Connection,
+) -> Vec<RequestOutcome> {
+    let mut outcomes = vec![];
+    loop {
+        let next = context.next.fetch_add(1, Ordering::Relaxed);
+        let Some(&(iteration, offset)) = context.schedule.get(next) else {
+            break;
+        };
+        if !offset.is_zero() {
+            sleep_until(context.start + offset).await;
+        }
+
+        let request_start = Instant::now();
+        let latency_start =
+            scheduled_start(context.is_burst, context.start + offset, request_start);
+        let mut bytes = 0;
+        let result = match send(&context, &mut connection, iteration).await {
+            Ok(response) => {
+                bytes = response.body().len() as u64;
+                context
+                    .benchmark
+                    .check_response(iteration, request_start, Response::from(response))
+                    .await
+            }
+            Err(err) => Err(err),
+        };
+        let sample = RequestSample {
+            completed: context.start.elapsed(),
+            latency: latency_start.elapsed(),
+            bytes,
+            ok: matches!(result, Ok(BenchmarkResult::Ok(_))),
+        };
+        if let Some(live_histograms) = &context.live_histograms {
+            live_histograms.record(&sample);
+        }
+        outcomes.push((sample, result));
+    }
+    outcomes
+}
+
+async fn send(
+    context: &WorkerContext,
+    connection: &mut Connection,
+    iteration: usize,
+) -> Result<http::Response<Bytes>, BenchmarkError> {
+    let request = context
+        .benchmark
+        .request_template(iteration)?
+        .build(context.protocol)?;
+    Ok(connection.send(request).await?)
+}
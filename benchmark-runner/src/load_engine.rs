@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+/// Implementation used to generate load against the webserver.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "type"
+)]
+pub enum LoadEngine {
+    /// A tokio task per request, sent through a shared reqwest client.
+    #[default]
+    Reqwest,
+    /// Per-worker loops on raw hyper connections. Over HTTP/1 each of the `workers` owns one
+    /// connection; over HTTP/2 there is one worker per stream and `workers` is ignored.
+    Hyper { workers: usize },
+    /// HTTP/1.1 pipelining: each of the `connections` writes `depth` requests back-to-back and
+    /// then reads their responses in order, as in TechEmpower's plaintext test.
+    Pipelined { connections: usize, depth: usize },
+}
@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Resolution used when turning a request rate into a request schedule.
+const SCHEDULE_TICK: Duration = Duration::from_millis(1);
+
+/// Shape of the load applied to a webserver over the course of a benchmark run.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "type"
+)]
+pub enum LoadProfile {
+    /// Issue every request at once.
+    Burst { iterations: usize },
+    /// Increase the request rate linearly from `start_rps` to `end_rps`.
+    Ramp {
+        start_rps: f64,
+        end_rps: f64,
+        duration_secs: u64,
+    },
+    /// Start at `start_rps` and add `step_rps` every `step_secs`, for `steps` steps.
+    Step {
+        start_rps: f64,
+        step_rps: f64,
+        step_secs: u64,
+        steps: u64,
+    },
+    /// Hold `base_rps`, jumping to `spike_rps` for `spike_secs` starting at `spike_start_secs`.
+    Spike {
+        base_rps: f64,
+        spike_rps: f64,
+        spike_start_secs: u64,
+        spike_secs: u64,
+        duration_secs: u64,
+    },
+    /// Hold `rps` for a long duration.
+    Soak { rps: f64, duration_secs: u64 },
+}
+
+impl LoadProfile {
+    pub fn is_burst(&self) -> bool {
+        matches!(self, LoadProfile::Burst { .. })
+    }
+
+    pub fn duration(&self) -> Duration {
+        let secs = match self {
+            LoadProfile::Burst { .. } => 0,
+            LoadProfile::Ramp { duration_secs, .. } => *duration_secs,
+            LoadProfile::Step {
+                step_secs, steps, ..
+            } => step_secs * steps,
+            LoadProfile::Spike { duration_secs, .. } => *duration_secs,
+            LoadProfile::Soak { duration_secs, .. } => *duration_secs,
+        };
+        Duration::from_secs(secs)
+    }
+
+    /// Requests per second the profile calls for at `elapsed` into the run.
+    pub fn rate_at(&self, elapsed: Duration) -> f64 {
+        let elapsed_secs = elapsed.as_secs_f64();
+        match self {
+            LoadProfile::Burst { .. } => 0.0,
+            LoadProfile::Ramp {
+                start_rps,
+                end_rps,
+                duration_secs,
+            } => {
+                let progress = (elapsed_secs / *duration_secs as f64).min(1.0);
+                start_rps + (end_rps - start_rps) * progress
+            }
+            LoadProfile::Step {
+                start_rps,
+                step_rps,
+                step_secs,
+                ..
+            } => {
+                // a zero step length steps every second rather than dividing by zero
+                let step = (elapsed.as_secs() / (*step_secs).max(1)) as f64;
+                start_rps + step_rps * step
+            }
+            LoadProfile::Spike {
+                base_rps,
+                spike_rps,
+                spike_start_secs,
+                spike_secs,
+                ..
+            } => {
+                let secs = elapsed.as_secs();
+                if secs >= *spike_start_secs && secs < spike_start_secs + spike_secs {
+                    *spike_rps
+                } else {
+                    *base_rps
+                }
+            }
+            LoadProfile::Soak { rps, .. } => *rps,
+        }
+    }
+
+    /// Offsets from the start of the run at which each request should be issued.
+    pub fn schedule(&self) -> Vec<Duration> {
+        if let LoadProfile::Burst { iterations } = self {
+            return vec![Duration::ZERO; *iterations];
+        }
+
+        let duration = self.duration();
+        let mut schedule = vec![];
+        let mut elapsed = Duration::ZERO;
+        let mut pending = 0f64;
+        while elapsed < duration {
+            pending += self.rate_at(elapsed) * SCHEDULE_TICK.as_secs_f64();
+            while pending >= 1.0 {
+                schedule.push(elapsed);
+                pending -= 1.0;
+            }
+            elapsed += SCHEDULE_TICK;
+        }
+        schedule
+    }
+
+    /// Total number of requests the profile will issue.
+    pub fn iterations(&self) -> usize {
+        match self {
+            LoadProfile::Burst { iterations } => *iterations,
+            _ => self.schedule().len(),
+        }
+    }
+}
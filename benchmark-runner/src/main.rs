@@ -1,37 +1,91 @@
 use std::{
+    borrow::Cow,
     collections::HashMap,
     fs::File,
-    sync::{Arc, Mutex},
+    net::SocketAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
     time::Duration,
 };
 
 use async_trait::async_trait;
 use env_logger::{self, Env};
 use futures::future::join_all;
+use http_body_util::BodyExt;
 use log::info;
 use reqwest::{Client, Response};
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Serialize, Serializer};
 use thiserror::Error;
-use tokio::time::{Instant, sleep};
+use tokio::{
+    task::spawn_blocking,
+    time::{Instant, sleep, sleep_until},
+};
 
 use crate::{
     benchmarks::{
-        download_binary::benchmark_download_binary,
-        matrix_multiplication::benchmark_matrix_multiplication, plaintext::benchmark_plaintext,
+        compression::{CompressionStats, generate_compressible_document},
+        database::generate_sqlite_database,
+        large_file::generate_large_files,
+        matrix_multiplication::{FloatError, MatrixCodecError},
+        sse::SseStats,
+        static_files::generate_static_files,
+        websocket::WebSocketStats,
+    },
+    calibration::{RunnerCeilings, apply_ceilings, calibrate},
+    connection::{ClientPool, ConnectionMode},
+    distributed::{
+        DEFAULT_WORKER_ADDR, DistributedError, Shard, run_distributed, run_worker, worker_addresses,
     },
     docker::{DockerError, run_webserver, stop_webserver},
     http::{HttpError, http_wait_for_url},
+    hyper_engine::{HyperEngineError, RequestTemplate, run_hyper_requests},
+    load_engine::LoadEngine,
+    load_profile::LoadProfile,
+    manifest::{ManifestError, load_manifest},
+    memory::{MemorySampler, MemoryStats},
+    pipeline::{PipelineError, run_pipelined_requests},
     process_manager::{ProcessManager, ProcessManagerError},
+    protocol::Protocol,
+    scenarios::scenarios,
+    time_series::{
+        LiveHistograms, RequestSample, TimeSeriesBucket, bucket_histograms, histogram_samples,
+    },
+    tls::{TlsError, TlsOptions, generate_certificates},
+    websocket::WebSocketError,
 };
 
 mod benchmarks;
+mod calibration;
+mod connection;
+mod distributed;
 mod docker;
 mod http;
+mod hyper_engine;
+mod load_engine;
+mod load_profile;
+mod manifest;
+mod memory;
+mod null_server;
+mod pipeline;
 mod process_manager;
+mod protocol;
+mod scenarios;
+mod time_series;
+mod tls;
+mod websocket;
 
 #[tokio::main]
 async fn main() {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("worker") {
+        let addr = args.get(2).map_or(DEFAULT_WORKER_ADDR, String::as_str);
+        run_worker(addr).await.unwrap();
+        return;
+    }
+
     info!("begin benchmarks");
     run_benchmarks().await.unwrap();
     info!("complete");
@@ -51,28 +105,179 @@ enum BenchmarkError {
     #[error("HTTP: {0}")]
     Http(#[from] HttpError),
 
+    #[error("Distributed: {0}")]
+    Distributed(#[from] DistributedError),
+
+    #[error("Manifest: {0}")]
+    Manifest(#[from] ManifestError),
+
+    #[error("TLS: {0}")]
+    Tls(#[from] TlsError),
+
     #[error("HTTP Reqwest: {0}")]
     HttpReqwest(#[from] reqwest::Error),
 
+    #[error("Hyper Engine: {0}")]
+    HyperEngine(#[from] HyperEngineError),
+
+    #[error("Matrix codec: {0}")]
+    MatrixCodec(#[from] MatrixCodecError),
+
+    #[error("Pipeline: {0}")]
+    Pipeline(#[from] PipelineError),
+
     #[error("JSON Serde: {0}")]
     Json(#[from] serde_json::Error),
+
+    #[error("SQLite: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("WebSocket: {0}")]
+    WebSocket(#[from] WebSocketError),
+
+    #[error("Connection limiter closed: {0}")]
+    Limiter(#[from] tokio::sync::AcquireError),
+
+    #[error("Task: {0}")]
+    Task(#[from] tokio::task::JoinError),
 }
 
 #[derive(Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 enum BenchmarkResult {
-    Ok(BenchmarkOkResult),
+    Ok(Box<BenchmarkOkResult>),
     InvalidStatusCode(u16),
     InvalidResponse(String),
     UnhandledError(String),
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 struct BenchmarkOkResult {
     #[serde(rename = "time_ms", serialize_with = "duration_as_millis")]
     time: Duration,
     iterations: usize,
+    errors: usize,
+    #[serde(flatten)]
+    options: Option<RunOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    runner_ceiling_rps: Option<f64>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    near_runner_ceiling: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    load_workers: Option<usize>,
+    /// Largest deviation from the reference result among accepted floating point values.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_float_error: Option<FloatError>,
+    /// Response sizes on the wire and decoded, for content coding runs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compression: Option<CompressionStats>,
+    /// Most requests waiting at once without latency rising well above the delay, for delay runs
+    /// with a time-varying load profile.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sustained_concurrency: Option<f64>,
+    /// Event timing and concurrent streams, for SSE runs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sse: Option<SseStats>,
+    /// Messages received per connection, for WebSocket runs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    websocket: Option<WebSocketStats>,
+    /// Webserver container memory over the run, for soak runs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memory: Option<MemoryStats>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    time_series: Vec<TimeSeriesBucket>,
+}
+
+impl BenchmarkOkResult {
+    fn rps(&self) -> f64 {
+        self.iterations as f64 / self.time.as_secs_f64()
+    }
+}
+
+/// Load engine settings for a single benchmark run, recorded alongside its result.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RunOptions {
+    engine: LoadEngine,
+    load_profile: LoadProfile,
+    connection_mode: ConnectionMode,
+    protocol: Protocol,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tls: Option<TlsOptions>,
+    #[serde(skip)]
+    null_server: Option<SocketAddr>,
+    /// Iterations this process issues when the run is spread over load workers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shard: Option<Shard>,
+    /// Filled as requests complete when a load worker streams its seconds to the coordinator.
+    #[serde(skip)]
+    live_histograms: Option<Arc<LiveHistograms>>,
+}
+
+impl RunOptions {
+    fn new(load_profile: LoadProfile) -> Self {
+        Self {
+            engine: LoadEngine::default(),
+            load_profile,
+            connection_mode: ConnectionMode::default(),
+            protocol: Protocol::default(),
+            tls: None,
+            null_server: None,
+            shard: None,
+            live_histograms: None,
+        }
+    }
+
+    fn burst(iterations: usize) -> Self {
+        Self::new(LoadProfile::Burst { iterations })
+    }
+
+    fn with_engine(mut self, engine: LoadEngine) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    fn with_connection_mode(mut self, connection_mode: ConnectionMode) -> Self {
+        self.connection_mode = connection_mode;
+        self
+    }
+
+    fn with_protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    fn with_tls(mut self, tls: TlsOptions) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    fn with_null_server(mut self, null_server: SocketAddr) -> Self {
+        self.null_server = Some(null_server);
+        self
+    }
+
+    /// Scheduled iterations this process issues along with their offsets from the start.
+    fn schedule(&self) -> Vec<(usize, Duration)> {
+        self.load_profile
+            .schedule()
+            .into_iter()
+            .enumerate()
+            .filter(|(iteration, _)| self.shard.is_none_or(|shard| shard.contains(*iteration)))
+            .collect()
+    }
+
+    /// Full URL of `path` on the webserver, on the TLS port when `tls` is set.
+    fn url(&self, path: &str) -> String {
+        if let Some(null_server) = self.null_server {
+            format!("http://{null_server}{path}")
+        } else if self.tls.is_some() {
+            format!("https://web:8443{path}")
+        } else {
+            format!("http://web:8000{path}")
+        }
+    }
 }
 
 type BenchmarkResults = HashMap<String, BenchmarkResult>;
@@ -99,11 +304,22 @@ where
 
 async fn run_benchmarks() -> Result<(), BenchmarkError> {
     let pm = ProcessManager::new()?;
+    generate_certificates()?;
+    // large files and the static tree take a while to write, keep them off the runtime's workers
+    spawn_blocking(generate_large_files).await??;
+    spawn_blocking(generate_static_files).await??;
+    generate_compressible_document()?;
+    let workers = worker_addresses().await?;
+    // the null server only runs inside this process, so there is no ceiling for workers
+    let ceilings = match workers {
+        Some(_) => RunnerCeilings::new(),
+        None => calibrate().await?,
+    };
 
     let mut all_results: HashMap<String, BenchmarkJsonResult> = HashMap::new();
 
     for name in ["rust-axum", "python-fastapi", "nodejs-express"] {
-        let results = run_benchmark(&pm, name).await;
+        let results = run_benchmark(&pm, name, &ceilings, workers.as_deref()).await;
         match results {
             Ok(results) => {
                 all_results.insert(name.to_string(), BenchmarkJsonResult::Success(results));
@@ -128,8 +344,12 @@ async fn run_benchmarks() -> Result<(), BenchmarkError> {
 async fn run_benchmark(
     pm: &ProcessManager,
     name: &str,
+    ceilings: &RunnerCeilings,
+    workers: Option<&[SocketAddr]>,
 ) -> Result<BenchmarkResults, BenchmarkError> {
-    let child = Arc::new(Mutex::new(run_webserver(name)?));
+    let manifest = load_manifest(name)?;
+    spawn_blocking(generate_sqlite_database).await??;
+    let child = Arc::new(Mutex::new(run_webserver(name, &manifest.sidecars).await?));
     pm.push(child.clone())?;
 
     http_wait_for_url(
@@ -141,36 +361,42 @@ async fn run_benchmark(
     sleep(Duration::from_secs(1)).await;
 
     let mut results: HashMap<String, BenchmarkResult> = HashMap::new();
-    match benchmark_plaintext(10000).await {
-        Ok(result) => results.insert("plaintext".to_string(), result),
-        Err(err) => results.insert(
-            "plaintext".to_string(),
-            BenchmarkResult::UnhandledError(format!("failed: {err}")),
-        ),
-    };
-
-    match benchmark_download_binary(1000).await {
-        Ok(result) => results.insert("downloadBinary".to_string(), result),
-        Err(err) => results.insert(
-            "downloadBinary".to_string(),
-            BenchmarkResult::UnhandledError(format!("failed: {err}")),
-        ),
-    };
-
-    match benchmark_matrix_multiplication(100).await {
-        Ok(result) => results.insert("matrixMultiplication".to_string(), result),
-        Err(err) => results.insert(
-            "matrixMultiplication".to_string(),
-            BenchmarkResult::UnhandledError(format!("failed: {err}")),
-        ),
-    };
+    for scenario in scenarios(&manifest) {
+        // soak runs are where memory growth shows
+        let sampler = matches!(scenario.options.load_profile, LoadProfile::Soak { .. })
+            .then(|| MemorySampler::start(name));
+        let mut result = match workers {
+            Some(workers) => run_distributed(&scenario, workers).await,
+            None => scenario.kind.run(scenario.options).await,
+        };
+        if let Some(sampler) = sampler {
+            let memory = sampler.stop().await;
+            if let Ok(BenchmarkResult::Ok(result)) = &mut result {
+                result.memory = memory;
+            }
+        }
+        record_result(&mut results, &scenario.name, result);
+    }
+    apply_ceilings(name, &mut results, ceilings);
 
-    stop_webserver(name)?;
+    stop_webserver(name, &manifest.sidecars).await?;
     pm.kill(child)?;
     sleep(Duration::from_secs(1)).await;
     Ok(results)
 }
 
+fn record_result(
+    results: &mut BenchmarkResults,
+    name: &str,
+    result: Result<BenchmarkResult, BenchmarkError>,
+) {
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => BenchmarkResult::UnhandledError(format!("failed: {err}")),
+    };
+    results.insert(name.to_string(), result);
+}
+
 #[async_trait]
 trait Benchmark: Send + Sync {
     async fn make_request(
@@ -184,53 +410,104 @@ trait Benchmark: Send + Sync {
         start: Instant,
         response: Response,
     ) -> Result<BenchmarkResult, BenchmarkError>;
+    /// The request for `iteration` as sent by engines that bypass reqwest.
+    fn request_template(
+        &self,
+        iteration: usize,
+    ) -> Result<Cow<'_, RequestTemplate>, BenchmarkError>;
 }
 
+/// Timing sample and validation result of a single request.
+type RequestOutcome = (RequestSample, Result<BenchmarkResult, BenchmarkError>);
+
 async fn run_requests(
-    iterations: usize,
+    options: RunOptions,
     benchmark: Arc<dyn Benchmark>,
 ) -> Result<BenchmarkResult, BenchmarkError> {
-    let client = Client::new();
+    let clients = Arc::new(ClientPool::new(&options)?);
 
-    let response = benchmark.make_request(client.clone(), 0).await?;
-    match benchmark
-        .check_response(0, Instant::now(), response)
-        .await?
     {
-        BenchmarkResult::Ok(_) => {}
-        other => return Ok(other),
+        let (client, _permit) = clients.acquire(0).await?;
+        let start = Instant::now();
+        let response = benchmark.make_request(client, 0).await?;
+        match benchmark.check_response(0, start, response).await? {
+            BenchmarkResult::Ok(_) => {}
+            other => return Ok(other),
+        }
+    }
+
+    if let Some(shard) = options.shard {
+        sleep(shard.until_start()).await;
+    }
+
+    match options.engine {
+        LoadEngine::Reqwest => run_reqwest_requests(options, benchmark, clients).await,
+        LoadEngine::Hyper { workers } => run_hyper_requests(options, benchmark, workers).await,
+        LoadEngine::Pipelined { connections, depth } => {
+            run_pipelined_requests(options, benchmark, connections, depth).await
+        }
     }
+}
 
+async fn run_reqwest_requests(
+    options: RunOptions,
+    benchmark: Arc<dyn Benchmark>,
+    clients: Arc<ClientPool>,
+) -> Result<BenchmarkResult, BenchmarkError> {
+    let schedule = options.schedule();
+    let iterations = schedule.len();
+    let is_burst = options.load_profile.is_burst();
     let start = Instant::now();
 
-    let futures = (0..iterations).map(|iteration| {
-        let client = client.clone();
+    let mut futures = Vec::with_capacity(iterations);
+    for (iteration, offset) in schedule {
+        if !offset.is_zero() {
+            sleep_until(start + offset).await;
+        }
+        let clients = clients.clone();
         let benchmark = benchmark.clone();
-        tokio::spawn(async move {
-            let start = Instant::now();
-            let response = benchmark.make_request(client, iteration).await?;
+        let live_histograms = options.live_histograms.clone();
+        futures.push(tokio::spawn(async move {
+            let (client, _permit) = clients.acquire(iteration).await?;
+
+            let request_start = Instant::now();
+            let latency_start = scheduled_start(is_burst, start + offset, request_start);
+            let bytes = Arc::new(AtomicU64::new(0));
             let result: Result<BenchmarkResult, BenchmarkError> =
-                benchmark.check_response(iteration, start, response).await;
-            result
-        })
-    });
+                match benchmark.make_request(client, iteration).await {
+                    Ok(response) => {
+                        let response = count_body_bytes(response, bytes.clone());
+                        benchmark
+                            .check_response(iteration, request_start, response)
+                            .await
+                    }
+                    Err(err) => Err(err),
+                };
+            let sample = RequestSample {
+                completed: start.elapsed(),
+                latency: latency_start.elapsed(),
+                bytes: bytes.load(Ordering::Relaxed),
+                ok: matches!(result, Ok(BenchmarkResult::Ok(_))),
+            };
+            if let Some(live_histograms) = live_histograms {
+                live_histograms.record(&sample);
+            }
+            Ok::<_, BenchmarkError>((sample, result))
+        }));
+    }
 
     let results = join_all(futures).await;
     let time = start.elapsed();
 
+    let mut outcomes = Vec::with_capacity(iterations);
     for result in results {
         match result {
-            Ok(result) => match result {
-                Ok(result) => match result {
-                    BenchmarkResult::Ok(_) => {}
-                    other => return Ok(other),
-                },
-                Err(err) => {
-                    return Ok(BenchmarkResult::InvalidResponse(format!(
-                        "one or more requests failed: {err}"
-                    )));
-                }
-            },
+            Ok(Ok(outcome)) => outcomes.push(outcome),
+            Ok(Err(err)) => {
+                return Ok(BenchmarkResult::InvalidResponse(format!(
+                    "one or more requests failed: {err}"
+                )));
+            }
             Err(err) => {
                 return Ok(BenchmarkResult::InvalidResponse(format!(
                     "one or more requests failed: {err}"
@@ -238,5 +515,63 @@ async fn run_requests(
             }
         }
     }
-    Ok(BenchmarkResult::Ok(BenchmarkOkResult { time, iterations }))
+
+    Ok(summarize_requests(options, time, outcomes))
+}
+
+/// Instant a request's latency is measured from. Bursts issue everything at once and measure from
+/// sending, whereas rate-limited profiles measure from when the request was due, so that time
+/// spent waiting behind slow responses counts instead of being hidden (coordinated omission).
+fn scheduled_start(is_burst: bool, scheduled: Instant, sent: Instant) -> Instant {
+    if is_burst { sent } else { scheduled.min(sent) }
+}
+
+/// Counts the body bytes of `response` into `bytes` as they are read, since chunked and streamed
+/// bodies have no length up front.
+fn count_body_bytes(response: Response, bytes: Arc<AtomicU64>) -> Response {
+    let response = ::http::Response::<reqwest::Body>::from(response).map(|body| {
+        reqwest::Body::wrap(body.map_frame(move |frame| {
+            if let Some(data) = frame.data_ref() {
+                bytes.fetch_add(data.len() as u64, Ordering::Relaxed);
+            }
+            frame
+        }))
+    });
+    Response::from(response)
+}
+
+/// Folds request outcomes into the run's result. A burst must succeed in full, time-varying
+/// profiles record errors over time instead.
+fn summarize_requests(
+    options: RunOptions,
+    time: Duration,
+    outcomes: Vec<RequestOutcome>,
+) -> BenchmarkResult {
+    let iterations = outcomes.len();
+    let mut samples = Vec::with_capacity(iterations);
+    let mut errors = 0;
+    for (sample, result) in outcomes {
+        if !sample.ok {
+            if options.load_profile.is_burst() {
+                return match result {
+                    Ok(result) => result,
+                    Err(err) => BenchmarkResult::InvalidResponse(format!(
+                        "one or more requests failed: {err}"
+                    )),
+                };
+            }
+            errors += 1;
+        }
+        samples.push(sample);
+    }
+
+    let histograms = histogram_samples(&samples);
+    BenchmarkResult::Ok(Box::new(BenchmarkOkResult {
+        time,
+        iterations,
+        errors,
+        options: Some(options),
+        time_series: bucket_histograms(&histograms),
+        ..Default::default()
+    }))
 }
@@ -0,0 +1,42 @@
+use std::{fs, io::ErrorKind};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ManifestError {
+    #[error("IO: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Capabilities a webserver declares in its `manifest.json`.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WebserverManifest {
+    /// Serves HTTP/2 with prior knowledge (h2c) on the plaintext port.
+    pub http2: bool,
+    /// Serves HTTPS on port 8443 using the certificates generated into `/assets/tls`.
+    pub tls: bool,
+    /// Services the runner starts on the benchmark network before the webserver.
+    pub sidecars: Vec<Sidecar>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Sidecar {
+    /// PostgreSQL seeded with the database benchmark tables, reachable at `postgres:5432` as
+    /// user, password and database `benchmark`.
+    Postgres,
+}
+
+pub fn load_manifest(name: &str) -> Result<WebserverManifest, ManifestError> {
+    let path = format!("/webservers/{name}/manifest.json");
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(WebserverManifest::default()),
+        Err(err) => Err(err.into()),
+    }
+}
@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use log::warn;
+use serde::Serialize;
+use tokio::{
+    sync::oneshot,
+    task::JoinHandle,
+    time::{Instant, interval},
+};
+
+use crate::docker::{container_memory_bytes, webserver_container};
+
+/// Time between memory samples, long enough for `docker stats` to return in between.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct MemorySample {
+    /// Seconds since sampling started.
+    pub second: u64,
+    pub bytes: u64,
+}
+
+/// Memory of the webserver container over a run, as reported by `docker stats`.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryStats {
+    pub start_bytes: u64,
+    pub end_bytes: u64,
+    pub peak_bytes: u64,
+    /// Difference between the last and the first sample, negative when memory was released.
+    pub growth_bytes: i64,
+    pub samples: Vec<MemorySample>,
+}
+
+impl MemoryStats {
+    fn from_samples(samples: Vec<MemorySample>) -> Option<Self> {
+        let start_bytes = samples.first()?.bytes;
+        let end_bytes = samples.last()?.bytes;
+        Some(Self {
+            start_bytes,
+            end_bytes,
+            peak_bytes: samples.iter().map(|sample| sample.bytes).max()?,
+            growth_bytes: end_bytes as i64 - start_bytes as i64,
+            samples,
+        })
+    }
+}
+
+/// Samples the memory of a webserver's container in the background until stopped.
+pub struct MemorySampler {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<Vec<MemorySample>>,
+}
+
+impl MemorySampler {
+    pub fn start(name: &str) -> Self {
+        let name = name.to_string();
+        let (stop, mut stopped) = oneshot::channel();
+        let task = tokio::spawn(async move {
+            let container = match webserver_container(&name).await {
+                Ok(container) => container,
+                Err(err) => {
+                    warn!("not sampling memory of {name}: {err}");
+                    return vec![];
+                }
+            };
+            let start = Instant::now();
+            let mut ticks = interval(SAMPLE_INTERVAL);
+            let mut samples = vec![];
+            loop {
+                // one last sample once stopped, so that the end of the run is covered
+                let stopping = tokio::select! {
+                    _ = &mut stopped => true,
+                    _ = ticks.tick() => false,
+                };
+                match container_memory_bytes(&container).await {
+                    Ok(bytes) => samples.push(MemorySample {
+                        second: start.elapsed().as_secs(),
+                        bytes,
+                    }),
+                    Err(err) => warn!("sampling memory of {name} failed: {err}"),
+                }
+                if stopping {
+                    return samples;
+                }
+            }
+        });
+        Self { stop, task }
+    }
+
+    /// Stops sampling, `None` when no sample could be taken.
+    pub async fn stop(self) -> Option<MemoryStats> {
+        let _ = self.stop.send(());
+        MemoryStats::from_samples(self.task.await.ok()?)
+    }
+}
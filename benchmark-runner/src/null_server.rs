@@ -0,0 +1,566 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fs,
+    hash::{BuildHasher, RandomState},
+    net::SocketAddr,
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures::{SinkExt, stream};
+use http::{
+    Method, Request, Response, StatusCode,
+    header::{
+        ACCEPT_ENCODING, CONNECTION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE,
+        ETAG, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RANGE,
+        SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE,
+    },
+};
+use http_body_util::{BodyExt, Full, StreamBody, combinators::BoxBody};
+use hyper::{
+    body::{Frame, Incoming},
+    service::service_fn,
+};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+};
+use log::{error, info};
+use tokio::{io::AsyncReadExt, net::TcpListener, sync::broadcast, time::sleep};
+use tokio_tungstenite::{
+    WebSocketStream,
+    tungstenite::{Message, handshake::derive_accept_key, protocol::Role},
+};
+
+use crate::benchmarks::{
+    compression::{self, ContentCoding},
+    database, delay, json, json_echo,
+    large_file::{
+        self, BYTERANGES_BOUNDARY, byteranges_body, content_range, parse_range, read_range,
+    },
+    matrix_multiplication::{self, MatrixMultiplicationOptions},
+    sse,
+    static_files::STATIC_DIR,
+    upload_binary,
+};
+
+type Body = BoxBody<Bytes, std::io::Error>;
+
+const LARGE_FILE_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Messages a WebSocket room buffers for connections that fall behind.
+const ROOM_CAPACITY: usize = 1024;
+
+/// Validators of the download binary, fixed since the asset does not change while the null server
+/// runs.
+const DOWNLOAD_BINARY_LAST_MODIFIED: &str = "Mon, 01 Jan 2024 00:00:00 GMT";
+const DOWNLOAD_BINARY_ETAG: &str = "\"null-server-download-binary\"";
+
+/// Files served by the null server, read once at startup.
+struct Assets {
+    download_binary: Bytes,
+    /// The compressible document in every content coding, encoded up front so that only the
+    /// runner pays for compression.
+    compressed: Vec<(ContentCoding, Bytes)>,
+    /// Products of the matrix multiplication requests the runner sends, by hash of the request
+    /// body, computed up front like the compressed documents.
+    matrix_products: HashMap<u64, (&'static str, Bytes)>,
+    matrix_hasher: RandomState,
+}
+
+impl Assets {
+    fn read(
+        matrix_workloads: &[(MatrixMultiplicationOptions, usize)],
+    ) -> Result<Self, std::io::Error> {
+        let document = fs::read(compression::DOCUMENT_PATH)?;
+        let mut compressed = vec![(ContentCoding::Identity, Bytes::from(document.clone()))];
+        for coding in ContentCoding::COMPRESSED {
+            compressed.push((coding, Bytes::from(coding.encode(&document)?)));
+        }
+
+        let matrix_hasher = RandomState::new();
+        let mut matrix_products = HashMap::new();
+        for (options, iterations) in matrix_workloads {
+            let exchanges = matrix_multiplication::reference_exchanges(options, *iterations)
+                .map_err(std::io::Error::other)?;
+            for (request, response) in exchanges {
+                matrix_products.insert(
+                    matrix_hasher.hash_one(&request),
+                    (options.encoding.content_type(), response),
+                );
+            }
+        }
+
+        Ok(Self {
+            download_binary: Bytes::from(fs::read("/assets/download-binary.png")?),
+            compressed,
+            matrix_products,
+            matrix_hasher,
+        })
+    }
+}
+
+/// Broadcast channel of a WebSocket room and the number of connections in it.
+struct Room {
+    sender: broadcast::Sender<Bytes>,
+    members: usize,
+}
+
+/// WebSocket rooms that have connections, by room name.
+#[derive(Default)]
+struct Rooms(Mutex<HashMap<String, Room>>);
+
+impl Rooms {
+    fn join(
+        self: &Arc<Self>,
+        room: &str,
+    ) -> (
+        Membership,
+        broadcast::Sender<Bytes>,
+        broadcast::Receiver<Bytes>,
+    ) {
+        let mut rooms = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let entry = rooms.entry(room.to_string()).or_insert_with(|| Room {
+            sender: broadcast::channel(ROOM_CAPACITY).0,
+            members: 0,
+        });
+        entry.members += 1;
+        let membership = Membership {
+            rooms: self.clone(),
+            room: room.to_string(),
+        };
+        (membership, entry.sender.clone(), entry.sender.subscribe())
+    }
+
+    /// Drops the room once its last connection left.
+    fn leave(&self, room: &str) {
+        let mut rooms = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(entry) = rooms.get_mut(room) {
+            entry.members -= 1;
+            if entry.members == 0 {
+                rooms.remove(room);
+            }
+        }
+    }
+}
+
+/// A connection's place in a room, left once dropped, whether the connection closed or its
+/// handshake never completed.
+struct Membership {
+    rooms: Arc<Rooms>,
+    room: String,
+}
+
+impl Drop for Membership {
+    fn drop(&mut self) {
+        self.rooms.leave(&self.room);
+    }
+}
+
+/// Starts a minimal HTTP/1.1 and h2c responder for the benchmark routes on its own runtime and
+/// thread, so that it competes with the runner for as little as possible. The products of the
+/// first `iterations` requests of each matrix workload are computed before it starts.
+pub fn start_null_server(
+    matrix_workloads: &[(MatrixMultiplicationOptions, usize)],
+) -> Result<SocketAddr, std::io::Error> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    listener.set_nonblocking(true)?;
+    let addr = listener.local_addr()?;
+    let assets = Arc::new(Assets::read(matrix_workloads)?);
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+
+    info!("null server listening on {addr}");
+    thread::spawn(move || {
+        runtime.block_on(async move {
+            if let Err(err) = serve(listener, assets).await {
+                error!("null server failed: {err}");
+            }
+        })
+    });
+    Ok(addr)
+}
+
+async fn serve(listener: std::net::TcpListener, assets: Arc<Assets>) -> Result<(), std::io::Error> {
+    let listener = TcpListener::from_std(listener)?;
+    let rooms = Arc::new(Rooms::default());
+    loop {
+        let (stream, _) = listener.accept().await?;
+        stream.set_nodelay(true)?;
+        let assets = assets.clone();
+        let rooms = rooms.clone();
+        tokio::spawn(async move {
+            let service =
+                service_fn(move |request| respond(request, assets.clone(), rooms.clone()));
+            let _ = auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .await;
+        });
+    }
+}
+
+async fn respond(
+    request: Request<Incoming>,
+    assets: Arc<Assets>,
+    rooms: Arc<Rooms>,
+) -> Result<Response<Body>, Infallible> {
+    if request.uri().path() == "/benchmark/ws" {
+        return Ok(upgrade_websocket(request, rooms));
+    }
+
+    let (parts, body) = request.into_parts();
+    let Ok(body) = body.collect().await.map(|body| body.to_bytes()) else {
+        return Ok(response(
+            StatusCode::BAD_REQUEST,
+            "text/plain",
+            Bytes::new(),
+        ));
+    };
+
+    let content_type = parts
+        .headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let response = match (parts.method, parts.uri.path()) {
+        (Method::GET, "/benchmark/health") => response(StatusCode::OK, "text/plain", Bytes::new()),
+        (Method::GET, "/benchmark/plain-text") => response(
+            StatusCode::OK,
+            "text/plain",
+            Bytes::from_static(b"Hello, World!"),
+        ),
+        (Method::GET, "/benchmark/download-binary") => {
+            let header = |name| {
+                parts
+                    .headers
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+            };
+            let not_modified = header(IF_NONE_MATCH) == Some(DOWNLOAD_BINARY_ETAG)
+                || header(IF_MODIFIED_SINCE) == Some(DOWNLOAD_BINARY_LAST_MODIFIED);
+            let mut response = if not_modified {
+                response(StatusCode::NOT_MODIFIED, "image/png", Bytes::new())
+            } else {
+                response(StatusCode::OK, "image/png", assets.download_binary.clone())
+            };
+            let headers = response.headers_mut();
+            headers.insert(ETAG, HeaderValue::from_static(DOWNLOAD_BINARY_ETAG));
+            headers.insert(
+                LAST_MODIFIED,
+                HeaderValue::from_static(DOWNLOAD_BINARY_LAST_MODIFIED),
+            );
+            response
+        }
+        (Method::GET, "/benchmark/compressed") => {
+            let accept_encoding = parts
+                .headers
+                .get(ACCEPT_ENCODING)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            let (coding, body) = assets
+                .compressed
+                .iter()
+                .find(|(coding, _)| {
+                    accept_encoding
+                        .split(',')
+                        .any(|token| token.trim() == coding.token())
+                })
+                .unwrap_or(&assets.compressed[0]);
+            let mut response = response(StatusCode::OK, "application/json", body.clone());
+            if !matches!(coding, ContentCoding::Identity) {
+                response
+                    .headers_mut()
+                    .insert(CONTENT_ENCODING, HeaderValue::from_static(coding.token()));
+            }
+            response
+        }
+        (Method::GET, "/benchmark/delay") => {
+            let delay_ms = parts
+                .uri
+                .query()
+                .and_then(|query| query.strip_prefix("ms="))
+                .and_then(|ms| ms.parse().ok());
+            match delay_ms {
+                Some(delay_ms) => {
+                    sleep(Duration::from_millis(delay_ms)).await;
+                    response(
+                        StatusCode::OK,
+                        "text/plain",
+                        Bytes::from(delay::reference_response(delay_ms)),
+                    )
+                }
+                None => response(StatusCode::BAD_REQUEST, "text/plain", Bytes::new()),
+            }
+        }
+        (Method::GET, "/benchmark/sse") => {
+            let param = |name: &str| {
+                parts.uri.query().and_then(|query| {
+                    query
+                        .split('&')
+                        .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+                        .and_then(|value| value.parse::<u64>().ok())
+                })
+            };
+            match (param("events"), param("interval_ms")) {
+                (Some(events), Some(interval_ms)) => sse_response(events as usize, interval_ms),
+                _ => response(StatusCode::BAD_REQUEST, "text/plain", Bytes::new()),
+            }
+        }
+        (Method::GET, "/benchmark/json") => match json::reference_response() {
+            Ok(body) => response(StatusCode::OK, "application/json", Bytes::from(body)),
+            Err(_) => response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "text/plain",
+                Bytes::new(),
+            ),
+        },
+        (Method::POST, "/benchmark/json-echo") => match json_echo::reference_response(&body) {
+            Ok(body) => response(StatusCode::OK, "application/json", Bytes::from(body)),
+            Err(_) => response(StatusCode::BAD_REQUEST, "text/plain", Bytes::new()),
+        },
+        (Method::POST, "/benchmark/matrix-multiplication") => {
+            let hash = assets.matrix_hasher.hash_one(&body);
+            match assets.matrix_products.get(&hash) {
+                Some((content_type, product)) => {
+                    response(StatusCode::OK, content_type, product.clone())
+                }
+                // requests outside the precomputed workloads are still answered, just not cheaply
+                None => match matrix_multiplication::reference_response(content_type, &body) {
+                    Ok((content_type, result)) => {
+                        response(StatusCode::OK, content_type, Bytes::from(result))
+                    }
+                    Err(_) => response(StatusCode::BAD_REQUEST, "text/plain", Bytes::new()),
+                },
+            }
+        }
+        (Method::POST, "/benchmark/upload-binary") => {
+            match upload_binary::reference_response(content_type, &body) {
+                Ok(result) => response(StatusCode::OK, "application/json", Bytes::from(result)),
+                Err(_) => response(StatusCode::BAD_REQUEST, "text/plain", Bytes::new()),
+            }
+        }
+        (Method::GET, path)
+            if path.starts_with("/benchmark/sqlite/") || path.starts_with("/benchmark/db/") =>
+        {
+            let route = path
+                .trim_start_matches("/benchmark/sqlite")
+                .trim_start_matches("/benchmark/db");
+            match database::reference_response(route, parts.uri.query()) {
+                Some((content_type, body)) => {
+                    response(StatusCode::OK, content_type, Bytes::from(body))
+                }
+                None => response(StatusCode::NOT_FOUND, "text/plain", Bytes::new()),
+            }
+        }
+        (Method::GET, path) if path.starts_with("/benchmark/static/") => {
+            let name = path.trim_start_matches("/benchmark/static/");
+            let file = if name.contains("..") {
+                Err(std::io::ErrorKind::NotFound.into())
+            } else {
+                tokio::fs::read(std::path::Path::new(STATIC_DIR).join(name)).await
+            };
+            match file {
+                Ok(file) => response(
+                    StatusCode::OK,
+                    "application/octet-stream",
+                    Bytes::from(file),
+                ),
+                Err(_) => response(StatusCode::NOT_FOUND, "text/plain", Bytes::new()),
+            }
+        }
+        (Method::GET, path) if path.starts_with("/benchmark/large-file/") => {
+            let range = parts
+                .headers
+                .get(RANGE)
+                .and_then(|value| value.to_str().ok());
+            match large_file_response(path, range).await {
+                Ok(response) => response,
+                Err(_) => response(StatusCode::NOT_FOUND, "text/plain", Bytes::new()),
+            }
+        }
+        _ => response(StatusCode::NOT_FOUND, "text/plain", Bytes::new()),
+    };
+    Ok(response)
+}
+
+/// Streams a generated large file from disk, or answers a range request for it.
+async fn large_file_response(
+    path: &str,
+    range: Option<&str>,
+) -> Result<Response<Body>, std::io::Error> {
+    let name = path.trim_start_matches("/benchmark/large-file/");
+    if name.contains('/') {
+        return Err(std::io::ErrorKind::NotFound.into());
+    }
+    let path = std::path::Path::new(large_file::LARGE_FILES_DIR).join(name);
+    let file = tokio::fs::File::open(&path).await?;
+    let size = file.metadata().await?.len();
+
+    let Some(range) = range else {
+        let chunks = stream::try_unfold(file, |mut file| async move {
+            let mut chunk = vec![0u8; LARGE_FILE_CHUNK_SIZE];
+            let read = file.read(&mut chunk).await?;
+            chunk.truncate(read);
+            Ok((read > 0).then(|| (Frame::data(Bytes::from(chunk)), file)))
+        });
+        let mut response = Response::new(StreamBody::new(chunks).boxed());
+        response.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/octet-stream"),
+        );
+        response
+            .headers_mut()
+            .insert(CONTENT_LENGTH, HeaderValue::from(size));
+        return Ok(response);
+    };
+
+    let Some(ranges) = parse_range(range, size) else {
+        return Ok(response(
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            "text/plain",
+            Bytes::new(),
+        ));
+    };
+    let mut file = file.into_std().await;
+    let mut parts = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        parts.push((start, end, read_range(&mut file, start, end)?));
+    }
+
+    if let [(start, end, bytes)] = &parts[..] {
+        let mut response = response(
+            StatusCode::PARTIAL_CONTENT,
+            "application/octet-stream",
+            Bytes::copy_from_slice(bytes),
+        );
+        response.headers_mut().insert(
+            CONTENT_RANGE,
+            HeaderValue::try_from(content_range(*start, *end, size))
+                .map_err(std::io::Error::other)?,
+        );
+        return Ok(response);
+    }
+    let mut response = Response::new(full(Bytes::from(byteranges_body(&parts, size))));
+    *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::try_from(format!(
+            "multipart/byteranges; boundary={BYTERANGES_BOUNDARY}"
+        ))
+        .map_err(std::io::Error::other)?,
+    );
+    Ok(response)
+}
+
+/// Accepts a WebSocket handshake and then echoes every message, or with a `room` query parameter
+/// relays it to every connection in the room.
+fn upgrade_websocket(mut request: Request<Incoming>, rooms: Arc<Rooms>) -> Response<Body> {
+    let Some(key) = request.headers().get(SEC_WEBSOCKET_KEY) else {
+        return response(StatusCode::BAD_REQUEST, "text/plain", Bytes::new());
+    };
+    let accept = derive_accept_key(key.as_bytes());
+    // joined before the handshake completes, so that nothing published afterwards is missed
+    let membership = request
+        .uri()
+        .query()
+        .and_then(|query| query.strip_prefix("room="))
+        .map(|room| rooms.join(room));
+
+    let upgrade = hyper::upgrade::on(&mut request);
+    tokio::spawn(async move {
+        let Ok(upgraded) = upgrade.await else {
+            return;
+        };
+        let socket =
+            WebSocketStream::from_raw_socket(TokioIo::new(upgraded), Role::Server, None).await;
+        match membership {
+            Some((_membership, sender, receiver)) => {
+                relay_websocket(socket, sender, receiver).await
+            }
+            None => echo_websocket(socket).await,
+        }
+    });
+
+    let mut response = Response::new(full(Bytes::new()));
+    *response.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+    let headers = response.headers_mut();
+    headers.insert(CONNECTION, HeaderValue::from_static("upgrade"));
+    headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+    if let Ok(accept) = HeaderValue::try_from(accept) {
+        headers.insert(SEC_WEBSOCKET_ACCEPT, accept);
+    }
+    response
+}
+
+async fn echo_websocket<S>(mut socket: WebSocketStream<S>)
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    // imported here since `StreamExt::boxed` collides with `BodyExt::boxed` elsewhere
+    use futures::StreamExt;
+
+    while let Some(Ok(message)) = socket.next().await {
+        if (message.is_binary() || message.is_text()) && socket.send(message).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn relay_websocket<S>(
+    socket: WebSocketStream<S>,
+    sender: broadcast::Sender<Bytes>,
+    mut receiver: broadcast::Receiver<Bytes>,
+) where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin + 'static,
+{
+    use futures::StreamExt;
+
+    let (mut sink, mut stream) = socket.split();
+    let forward = tokio::spawn(async move {
+        while let Ok(payload) = receiver.recv().await {
+            if sink.send(Message::Binary(payload)).await.is_err() {
+                break;
+            }
+        }
+    });
+    while let Some(Ok(message)) = stream.next().await {
+        if message.is_binary() || message.is_text() {
+            let _ = sender.send(message.into_data());
+        }
+    }
+    forward.abort();
+}
+
+/// Streams the reference events, the first right away and the rest `interval_ms` apart.
+fn sse_response(events: usize, interval_ms: u64) -> Response<Body> {
+    let events = sse::reference_events(events).into_iter().enumerate();
+    let frames = stream::unfold(events, move |mut events| async move {
+        let (sequence, event) = events.next()?;
+        if sequence > 0 {
+            sleep(Duration::from_millis(interval_ms)).await;
+        }
+        Some((Ok(Frame::data(Bytes::from(event))), events))
+    });
+    let mut response = Response::new(StreamBody::new(frames).boxed());
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
+    response
+}
+
+fn full(body: Bytes) -> Body {
+    Full::new(body).map_err(|never| match never {}).boxed()
+}
+
+fn response(status: StatusCode, content_type: &'static str, body: Bytes) -> Response<Body> {
+    let mut response = Response::new(full(body));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    response
+}
@@ -0,0 +1,370 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use bytes::{Buf, Bytes, BytesMut};
+use http::{
+    StatusCode,
+    header::{CONTENT_LENGTH, HeaderName, HeaderValue, TRANSFER_ENCODING},
+    uri::Authority,
+};
+use httparse::Status;
+use reqwest::Response;
+use thiserror::Error;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    time::{Instant, sleep_until},
+};
+
+use crate::{
+    Benchmark, BenchmarkError, BenchmarkResult, RequestOutcome, RunOptions,
+    connection::ConnectionMode,
+    hyper_engine::connect,
+    protocol::Protocol,
+    scheduled_start, summarize_requests,
+    time_series::{LiveHistograms, RequestSample},
+};
+
+const MAX_HEADERS: usize = 64;
+
+#[derive(Error, Debug)]
+pub enum PipelineError {
+    #[error("IO: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Parse: {0}")]
+    Parse(#[from] httparse::Error),
+
+    #[error("HTTP: {0}")]
+    Http(#[from] http::Error),
+
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+
+    #[error("Unsupported: {0}")]
+    Unsupported(String),
+}
+
+/// Keep-alive connection that requests are pipelined on, buffering what has been read past the
+/// current response.
+struct PipelinedConnection {
+    stream: TcpStream,
+    buffer: BytesMut,
+}
+
+impl PipelinedConnection {
+    async fn write(&mut self, requests: &[u8]) -> Result<(), PipelineError> {
+        self.stream.write_all(requests).await?;
+        Ok(())
+    }
+
+    async fn read_response(&mut self) -> Result<http::Response<Bytes>, PipelineError> {
+        loop {
+            if let Some(response) = self.parse_response()? {
+                return Ok(response);
+            }
+            if self.stream.read_buf(&mut self.buffer).await? == 0 {
+                return Err(PipelineError::InvalidResponse(
+                    "connection closed with responses outstanding".to_string(),
+                ));
+            }
+        }
+    }
+
+    /// Takes the next final response off the buffer, `None` if more bytes are needed. Interim
+    /// responses such as `100 Continue` precede it and are skipped, except for `101 Switching
+    /// Protocols`, after which no further HTTP responses follow.
+    fn parse_response(&mut self) -> Result<Option<http::Response<Bytes>>, PipelineError> {
+        loop {
+            let Some(response) = self.parse_message()? else {
+                return Ok(None);
+            };
+            let status = response.status();
+            if !status.is_informational() || status == StatusCode::SWITCHING_PROTOCOLS {
+                return Ok(Some(response));
+            }
+        }
+    }
+
+    /// Takes the next complete response, interim or final, off the buffer, `None` if more bytes
+    /// are needed.
+    fn parse_message(&mut self) -> Result<Option<http::Response<Bytes>>, PipelineError> {
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut parsed = httparse::Response::new(&mut headers);
+        let Status::Complete(head_len) = parsed.parse(&self.buffer)? else {
+            return Ok(None);
+        };
+
+        let status = StatusCode::from_u16(parsed.code.unwrap_or_default())
+            .map_err(|err| PipelineError::InvalidResponse(err.to_string()))?;
+        let mut response = http::Response::builder().status(status);
+        for header in parsed.headers.iter() {
+            response = response.header(
+                HeaderName::from_bytes(header.name.as_bytes()).map_err(http::Error::from)?,
+                HeaderValue::from_bytes(header.value).map_err(http::Error::from)?,
+            );
+        }
+        let response = response.body(())?;
+
+        let body = &self.buffer[head_len..];
+        let (body, body_len) = if is_chunked(response.headers()) {
+            match decode_chunked(body)? {
+                Some(decoded) => decoded,
+                None => return Ok(None),
+            }
+        } else if let Some(content_length) = response.headers().get(CONTENT_LENGTH) {
+            let content_length = content_length
+                .to_str()
+                .ok()
+                .and_then(|value| value.parse::<usize>().ok())
+                .ok_or_else(|| {
+                    PipelineError::InvalidResponse(format!(
+                        "invalid content-length {content_length:?}"
+                    ))
+                })?;
+            if body.len() < content_length {
+                return Ok(None);
+            }
+            (
+                Bytes::copy_from_slice(&body[..content_length]),
+                content_length,
+            )
+        } else if status.is_informational()
+            || status == StatusCode::NO_CONTENT
+            || status == StatusCode::NOT_MODIFIED
+        {
+            (Bytes::new(), 0)
+        } else {
+            return Err(PipelineError::Unsupported(
+                "response delimited by closing the connection".to_string(),
+            ));
+        };
+
+        self.buffer.advance(head_len + body_len);
+        let (parts, ()) = response.into_parts();
+        Ok(Some(http::Response::from_parts(parts, body)))
+    }
+}
+
+fn is_chunked(headers: &http::HeaderMap) -> bool {
+    headers
+        .get_all(TRANSFER_ENCODING)
+        .iter()
+        .any(|value| value.as_bytes().eq_ignore_ascii_case(b"chunked"))
+}
+
+/// Decodes a chunked body, returning it with the number of bytes it took up on the wire, or
+/// `None` if it is not complete yet. Trailers are skipped.
+fn decode_chunked(mut input: &[u8]) -> Result<Option<(Bytes, usize)>, PipelineError> {
+    let total = input.len();
+    let mut body = BytesMut::new();
+    loop {
+        let (offset, size) = match httparse::parse_chunk_size(input) {
+            Ok(Status::Complete(chunk)) => chunk,
+            Ok(Status::Partial) => return Ok(None),
+            Err(_) => {
+                return Err(PipelineError::InvalidResponse(
+                    "invalid chunk size".to_string(),
+                ));
+            }
+        };
+        input = &input[offset..];
+        if size == 0 {
+            break;
+        }
+        let size = size as usize;
+        if input.len() < size + 2 {
+            return Ok(None);
+        }
+        body.extend_from_slice(&input[..size]);
+        input = &input[size + 2..];
+    }
+
+    // trailer fields end with an empty line
+    loop {
+        let Some(end) = input.windows(2).position(|window| window == b"\r\n") else {
+            return Ok(None);
+        };
+        input = &input[end + 2..];
+        if end == 0 {
+            return Ok(Some((body.freeze(), total - input.len())));
+        }
+    }
+}
+
+struct PipelineContext {
+    benchmark: Arc<dyn Benchmark>,
+    authority: Authority,
+    depth: usize,
+    schedule: Vec<(usize, Duration)>,
+    next: AtomicUsize,
+    start: Instant,
+    is_burst: bool,
+    live_histograms: Option<Arc<LiveHistograms>>,
+}
+
+/// Runs the load profile over `connections` keep-alive connections, writing up to `depth`
+/// scheduled requests at once on each and reading their responses back in order.
+pub async fn run_pipelined_requests(
+    options: RunOptions,
+    benchmark: Arc<dyn Benchmark>,
+    connections: usize,
+    depth: usize,
+) -> Result<BenchmarkResult, BenchmarkError> {
+    if options.tls.is_some() {
+        return Err(PipelineError::Unsupported("TLS".to_string()).into());
+    }
+    if !matches!(options.protocol, Protocol::Http1) {
+        return Err(PipelineError::Unsupported(format!("protocol {:?}", options.protocol)).into());
+    }
+    if !matches!(options.connection_mode, ConnectionMode::KeepAlive { .. }) {
+        return Err(PipelineError::Unsupported(format!(
+            "connection mode {:?}",
+            options.connection_mode
+        ))
+        .into());
+    }
+
+    let authority = benchmark.request_template(0)?.authority().clone();
+    let mut pipelined = vec![];
+    for _ in 0..connections {
+        pipelined.push(PipelinedConnection {
+            stream: connect(&authority).await?,
+            buffer: BytesMut::new(),
+        });
+    }
+
+    let schedule = options.schedule();
+    let iterations = schedule.len();
+    let context = Arc::new(PipelineContext {
+        benchmark,
+        authority,
+        depth: depth.max(1),
+        schedule,
+        next: AtomicUsize::new(0),
+        start: Instant::now(),
+        is_burst: options.load_profile.is_burst(),
+        live_histograms: options.live_histograms.clone(),
+    });
+
+    let handles: Vec<_> = pipelined
+        .into_iter()
+        .map(|connection| tokio::spawn(run_connection(context.clone(), connection)))
+        .collect();
+
+    let mut outcomes = Vec::with_capacity(iterations);
+    for handle in handles {
+        match handle.await {
+            Ok(connection_outcomes) => outcomes.extend(connection_outcomes),
+            Err(err) => {
+                return Ok(BenchmarkResult::InvalidResponse(format!(
+                    "one or more requests failed: {err}"
+                )));
+            }
+        }
+    }
+    let time = context.start.elapsed();
+
+    Ok(summarize_requests(options, time, outcomes))
+}
+
+async fn run_connection(
+    context: Arc<PipelineContext>,
+    mut connection: PipelinedConnection,
+) -> Vec<RequestOutcome> {
+    let mut outcomes = vec![];
+    let mut requests = Vec::new();
+    loop {
+        let first = context.next.fetch_add(context.depth, Ordering::Relaxed);
+        let Some(batch) = context.schedule.get(first..) else {
+            break;
+        };
+        if batch.is_empty() {
+            break;
+        }
+        let batch = &batch[..batch.len().min(context.depth)];
+        // the batch goes out together once its last request is due
+        let (_, last_offset) = batch[batch.len() - 1];
+        if !last_offset.is_zero() {
+            sleep_until(context.start + last_offset).await;
+        }
+
+        let request_start = Instant::now();
+        requests.clear();
+        let written = match encode_batch(&context, batch, &mut requests) {
+            Ok(()) => connection
+                .write(&requests)
+                .await
+                .map_err(BenchmarkError::from),
+            Err(err) => Err(err),
+        };
+
+        // once the connection failed the responses of the rest of the batch are lost too
+        let mut failed = written.err().map(|err| err.to_string());
+        for &(iteration, offset) in batch {
+            let latency_start =
+                scheduled_start(context.is_burst, context.start + offset, request_start);
+            let mut bytes = 0;
+            let result = match &failed {
+                Some(err) => Ok(BenchmarkResult::InvalidResponse(format!(
+                    "pipelined request failed: {err}"
+                ))),
+                None => match connection.read_response().await {
+                    Ok(response) => {
+                        bytes = response.body().len() as u64;
+                        context
+                            .benchmark
+                            .check_response(iteration, request_start, Response::from(response))
+                            .await
+                    }
+                    Err(err) => {
+                        failed = Some(err.to_string());
+                        Err(err.into())
+                    }
+                },
+            };
+            let sample = RequestSample {
+                completed: context.start.elapsed(),
+                latency: latency_start.elapsed(),
+                bytes,
+                ok: matches!(result, Ok(BenchmarkResult::Ok(_))),
+            };
+            if let Some(live_histograms) = &context.live_histograms {
+                live_histograms.record(&sample);
+            }
+            outcomes.push((sample, result));
+        }
+        // responses after a failure can no longer be matched to their requests, start over
+        if failed.is_some() {
+            match connect(&context.authority).await {
+                Ok(stream) => {
+                    connection = PipelinedConnection {
+                        stream,
+                        buffer: BytesMut::new(),
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+    }
+    outcomes
+}
+
+fn encode_batch(
+    context: &PipelineContext,
+    batch: &[(usize, Duration)],
+    requests: &mut Vec<u8>,
+) -> Result<(), BenchmarkError> {
+    for &(iteration, _) in batch {
+        context
+            .benchmark
+            .request_template(iteration)?
+            .encode_http1(requests);
+    }
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use reqwest::ClientBuilder;
+use serde::{Deserialize, Serialize};
+
+/// HTTP version the load engine speaks to the webserver.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "type"
+)]
+pub enum Protocol {
+    #[default]
+    Http1,
+    /// HTTP/2 spread over `connections` connections with at most `streams_per_connection`
+    /// concurrent streams each. Cleartext uses prior knowledge (h2c), TLS negotiates via ALPN.
+    Http2 {
+        connections: usize,
+        streams_per_connection: usize,
+    },
+}
+
+impl Protocol {
+    pub fn is_http2(&self) -> bool {
+        matches!(self, Protocol::Http2 { .. })
+    }
+
+    pub fn configure(&self, builder: ClientBuilder) -> ClientBuilder {
+        match self {
+            Protocol::Http1 => builder.http1_only(),
+            // over TLS this restricts the ALPN offer to "h2"
+            Protocol::Http2 { .. } => builder.http2_prior_knowledge(),
+        }
+    }
+}
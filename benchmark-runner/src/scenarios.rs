@@ -0,0 +1,543 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BenchmarkError, BenchmarkResult, RunOptions,
+    benchmarks::{
+        compression::{ContentCoding, benchmark_compression},
+        conditional_get::{CacheValidator, ConditionalGetOptions, benchmark_conditional_get},
+        database::{DatabaseBackend, DatabaseOptions, DatabaseQuery, benchmark_database},
+        delay::{DelayOptions, benchmark_delay},
+        download_binary::benchmark_download_binary,
+        json::benchmark_json,
+        json_echo::{JsonDocumentShape, benchmark_json_echo},
+        large_file::{ByteRange, LargeFileOptions, benchmark_large_file},
+        matrix_multiplication::{
+            FloatTolerance, MatrixEncoding, MatrixMultiplicationOptions,
+            benchmark_matrix_multiplication,
+        },
+        plaintext::benchmark_plaintext,
+        sse::{SseOptions, benchmark_sse},
+        static_files::benchmark_static_files,
+        upload_binary::{UploadEncoding, UploadOptions, UploadPayload, benchmark_upload_binary},
+        websocket::{WebSocketMode, WebSocketOptions, benchmark_websocket},
+    },
+    connection::ConnectionMode,
+    load_engine::LoadEngine,
+    load_profile::LoadProfile,
+    manifest::{Sidecar, WebserverManifest},
+    protocol::Protocol,
+    tls::TlsOptions,
+};
+
+/// Benchmark implementation a scenario runs.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum BenchmarkKind {
+    Plaintext,
+    DownloadBinary,
+    Json,
+    JsonEcho(JsonDocumentShape),
+    MatrixMultiplication(MatrixMultiplicationOptions),
+    UploadBinary(UploadOptions),
+    LargeFile(LargeFileOptions),
+    StaticFiles,
+    ConditionalGet(ConditionalGetOptions),
+    Compression(ContentCoding),
+    Delay(DelayOptions),
+    Database(DatabaseOptions),
+    WebSocket(WebSocketOptions),
+    Sse(SseOptions),
+}
+
+impl BenchmarkKind {
+    pub async fn run(&self, options: RunOptions) -> Result<BenchmarkResult, BenchmarkError> {
+        match self {
+            BenchmarkKind::Plaintext => benchmark_plaintext(options).await,
+            BenchmarkKind::DownloadBinary => benchmark_download_binary(options).await,
+            BenchmarkKind::Json => benchmark_json(options).await,
+            BenchmarkKind::JsonEcho(shape) => benchmark_json_echo(options, *shape).await,
+            BenchmarkKind::MatrixMultiplication(matrix_options) => {
+                benchmark_matrix_multiplication(options, *matrix_options).await
+            }
+            BenchmarkKind::UploadBinary(upload_options) => {
+                benchmark_upload_binary(options, *upload_options).await
+            }
+            BenchmarkKind::LargeFile(large_file_options) => {
+                benchmark_large_file(options, *large_file_options).await
+            }
+            BenchmarkKind::StaticFiles => benchmark_static_files(options).await,
+            BenchmarkKind::ConditionalGet(conditional_options) => {
+                benchmark_conditional_get(options, *conditional_options).await
+            }
+            BenchmarkKind::Compression(coding) => benchmark_compression(options, *coding).await,
+            BenchmarkKind::Delay(delay_options) => benchmark_delay(options, *delay_options).await,
+            BenchmarkKind::Database(database_options) => {
+                benchmark_database(options, *database_options).await
+            }
+            BenchmarkKind::WebSocket(websocket_options) => {
+                benchmark_websocket(options, *websocket_options).await
+            }
+            BenchmarkKind::Sse(sse_options) => benchmark_sse(options, *sse_options).await,
+        }
+    }
+}
+
+/// A named benchmark run together with the load engine settings it runs with.
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub name: String,
+    pub kind: BenchmarkKind,
+    pub options: RunOptions,
+}
+
+fn scenario(name: impl Into<String>, kind: BenchmarkKind, options: RunOptions) -> Scenario {
+    Scenario {
+        name: name.into(),
+        kind,
+        options,
+    }
+}
+
+/// Every scenario a webserver with the given manifest takes part in, in run order.
+pub fn scenarios(manifest: &WebserverManifest) -> Vec<Scenario> {
+    use BenchmarkKind::*;
+
+    let matrix = MatrixMultiplicationOptions::square(101, FloatTolerance::DEFAULT);
+    let mut scenarios = vec![
+        scenario("plaintext", Plaintext, RunOptions::burst(10000)),
+        scenario("downloadBinary", DownloadBinary, RunOptions::burst(1000)),
+        scenario("json", Json, RunOptions::burst(10000)),
+        scenario(
+            "jsonEchoSmall",
+            JsonEcho(JsonDocumentShape {
+                size_bytes: 1024,
+                depth: 4,
+            }),
+            RunOptions::burst(5000),
+        ),
+        scenario(
+            "jsonEchoLarge",
+            JsonEcho(JsonDocumentShape {
+                size_bytes: 256 * 1024,
+                depth: 16,
+            }),
+            RunOptions::burst(500),
+        ),
+        scenario(
+            "matrixMultiplication",
+            MatrixMultiplication(matrix),
+            RunOptions::burst(100),
+        ),
+        scenario(
+            "matrixMultiplicationExact",
+            MatrixMultiplication(MatrixMultiplicationOptions::square(
+                101,
+                FloatTolerance::EXACT,
+            )),
+            RunOptions::burst(100),
+        ),
+        scenario(
+            "matrixMultiplicationRectangular",
+            MatrixMultiplication(MatrixMultiplicationOptions {
+                rows: 50,
+                inner: 200,
+                columns: 20,
+                tolerance: FloatTolerance::DEFAULT,
+                encoding: MatrixEncoding::Json,
+            }),
+            RunOptions::burst(100),
+        ),
+        // same workload without JSON float formatting and parsing
+        scenario(
+            "matrixMultiplicationBinary",
+            MatrixMultiplication(matrix.with_encoding(MatrixEncoding::Binary)),
+            RunOptions::burst(100),
+        ),
+    ];
+
+    for (name, payload, encoding, iterations) in uploads() {
+        scenarios.push(scenario(
+            format!("uploadBinary{name}"),
+            UploadBinary(UploadOptions { payload, encoding }),
+            RunOptions::burst(iterations),
+        ));
+    }
+
+    scenarios.push(scenario(
+        "staticFiles",
+        StaticFiles,
+        RunOptions::burst(10000),
+    ));
+
+    for (name, validator) in [
+        ("ETag", CacheValidator::ETag),
+        ("LastModified", CacheValidator::LastModified),
+    ] {
+        // a hit skips the body, a miss sends it like downloadBinary does
+        for (outcome, hit, iterations) in [("Hit", true, 10000), ("Miss", false, 1000)] {
+            scenarios.push(scenario(
+                format!("conditionalGet{name}{outcome}"),
+                ConditionalGet(ConditionalGetOptions { validator, hit }),
+                RunOptions::burst(iterations),
+            ));
+        }
+    }
+
+    for (name, coding) in [
+        ("Identity", ContentCoding::Identity),
+        ("Gzip", ContentCoding::Gzip),
+        ("Brotli", ContentCoding::Brotli),
+        ("Zstd", ContentCoding::Zstd),
+    ] {
+        scenarios.push(scenario(
+            format!("compression{name}"),
+            Compression(coding),
+            RunOptions::burst(2000),
+        ));
+    }
+
+    // updates run last so that the reads see the rows as generated
+    for (name, query, iterations) in [
+        ("Single", DatabaseQuery::Single, 10000),
+        ("Queries20", DatabaseQuery::Multiple { queries: 20 }, 2000),
+        ("Fortunes", DatabaseQuery::Fortunes, 10000),
+        ("Updates20", DatabaseQuery::Updates { queries: 20 }, 1000),
+    ] {
+        scenarios.push(scenario(
+            format!("sqlite{name}"),
+            Database(DatabaseOptions {
+                backend: DatabaseBackend::Sqlite,
+                query,
+            }),
+            RunOptions::burst(iterations),
+        ));
+    }
+
+    if manifest.sidecars.contains(&Sidecar::Postgres) {
+        for (name, query, iterations) in [
+            ("Single", DatabaseQuery::Single, 10000),
+            ("Queries20", DatabaseQuery::Multiple { queries: 20 }, 2000),
+            ("Updates20", DatabaseQuery::Updates { queries: 20 }, 1000),
+        ] {
+            scenarios.push(scenario(
+                format!("db{name}"),
+                Database(DatabaseOptions {
+                    backend: DatabaseBackend::Postgres,
+                    query,
+                }),
+                RunOptions::burst(iterations),
+            ));
+        }
+    }
+
+    // connections are opened before the run, so only the messages on them are measured
+    for (name, mode, connections, messages) in [
+        ("Echo", WebSocketMode::Echo, 100, 100),
+        ("Stream", WebSocketMode::Stream, 10, 10000),
+        ("Broadcast", WebSocketMode::Broadcast, 500, 100),
+    ] {
+        let websocket_options = WebSocketOptions {
+            mode,
+            connections,
+            messages,
+            message_bytes: 64,
+        };
+        scenarios.push(scenario(
+            format!("websocket{name}"),
+            WebSocket(websocket_options),
+            RunOptions::burst(websocket_options.iterations()),
+        ));
+    }
+
+    // every stream stays open for a second, so the stepped rate is roughly the open stream count
+    let sse = Sse(SseOptions {
+        events: 10,
+        interval_ms: 100,
+    });
+    scenarios.push(scenario("sse", sse, RunOptions::burst(1000)));
+    scenarios.push(scenario(
+        "sseStep",
+        sse,
+        RunOptions::new(LoadProfile::Step {
+            start_rps: 500.0,
+            step_rps: 1000.0,
+            step_secs: 5,
+            steps: 10,
+        }),
+    ));
+
+    // every request waits on the server, so in-flight requests pile up unless handlers yield
+    let delay = Delay(DelayOptions { delay_ms: 100 });
+    scenarios.push(scenario("delay", delay, RunOptions::burst(1000)));
+    scenarios.push(scenario(
+        "delayStep",
+        delay,
+        RunOptions::new(LoadProfile::Step {
+            start_rps: 500.0,
+            step_rps: 1000.0,
+            step_secs: 5,
+            steps: 10,
+        }),
+    ));
+
+    for (name, large_file_options, iterations) in large_files() {
+        scenarios.push(scenario(
+            format!("largeFile{name}"),
+            LargeFile(large_file_options),
+            RunOptions::burst(iterations),
+        ));
+    }
+
+    // per-request overhead dominates small matrices, compute dominates large ones
+    for (size, iterations) in matrix_sizes() {
+        scenarios.push(scenario(
+            format!("matrixMultiplication{size}"),
+            MatrixMultiplication(MatrixMultiplicationOptions::square(
+                size,
+                FloatTolerance::DEFAULT,
+            )),
+            RunOptions::burst(iterations),
+        ));
+    }
+
+    for (name, profile) in load_profiles() {
+        scenarios.push(scenario(
+            format!("plaintext{name}"),
+            Plaintext,
+            RunOptions::new(profile),
+        ));
+    }
+
+    for (name, connection_mode) in connection_modes() {
+        scenarios.push(scenario(
+            format!("plaintext{name}"),
+            Plaintext,
+            RunOptions::burst(5000).with_connection_mode(connection_mode),
+        ));
+    }
+
+    let hyper = LoadEngine::Hyper { workers: 256 };
+    scenarios.push(scenario(
+        "plaintextHyper",
+        Plaintext,
+        RunOptions::burst(10000).with_engine(hyper),
+    ));
+    scenarios.push(scenario(
+        "downloadBinaryHyper",
+        DownloadBinary,
+        RunOptions::burst(1000).with_engine(hyper),
+    ));
+
+    // comparable with TechEmpower's plaintext test, which pipelines 16 requests per connection
+    scenarios.push(scenario(
+        "plaintextPipelined",
+        Plaintext,
+        RunOptions::burst(10000).with_engine(LoadEngine::Pipelined {
+            connections: 64,
+            depth: 16,
+        }),
+    ));
+
+    let http2 = Protocol::Http2 {
+        connections: 4,
+        streams_per_connection: 100,
+    };
+    if manifest.http2 {
+        scenarios.push(scenario(
+            "plaintextH2c",
+            Plaintext,
+            RunOptions::burst(10000).with_protocol(http2),
+        ));
+        scenarios.push(scenario(
+            "downloadBinaryH2c",
+            DownloadBinary,
+            RunOptions::burst(1000).with_protocol(http2),
+        ));
+        scenarios.push(scenario(
+            "plaintextHyperH2c",
+            Plaintext,
+            RunOptions::burst(10000)
+                .with_protocol(http2)
+                .with_engine(hyper),
+        ));
+    }
+
+    if manifest.tls {
+        let tls = TlsOptions {
+            session_resumption: true,
+        };
+        scenarios.push(scenario(
+            "plaintextTls",
+            Plaintext,
+            RunOptions::burst(10000).with_tls(tls),
+        ));
+        scenarios.push(scenario(
+            "downloadBinaryTls",
+            DownloadBinary,
+            RunOptions::burst(1000).with_tls(tls),
+        ));
+        scenarios.push(scenario(
+            "matrixMultiplicationTls",
+            MatrixMultiplication(matrix),
+            RunOptions::burst(100).with_tls(tls),
+        ));
+
+        if manifest.http2 {
+            scenarios.push(scenario(
+                "plaintextH2",
+                Plaintext,
+                RunOptions::burst(10000).with_protocol(http2).with_tls(tls),
+            ));
+        }
+
+        // every request opens a new connection, so the difference is the handshake cost
+        for (name, session_resumption) in [("Full", false), ("Resumed", true)] {
+            scenarios.push(scenario(
+                format!("plaintextTlsHandshake{name}"),
+                Plaintext,
+                RunOptions::burst(5000)
+                    .with_connection_mode(ConnectionMode::Close)
+                    .with_tls(TlsOptions { session_resumption }),
+            ));
+        }
+    }
+
+    scenarios
+}
+
+/// Time-varying load shapes run against the plaintext endpoint.
+fn load_profiles() -> Vec<(&'static str, LoadProfile)> {
+    vec![
+        (
+            "Ramp",
+            LoadProfile::Ramp {
+                start_rps: 100.0,
+                end_rps: 5000.0,
+                duration_secs: 30,
+            },
+        ),
+        (
+            "Step",
+            LoadProfile::Step {
+                start_rps: 500.0,
+                step_rps: 500.0,
+                step_secs: 5,
+                steps: 10,
+            },
+        ),
+        (
+            "Spike",
+            LoadProfile::Spike {
+                base_rps: 500.0,
+                spike_rps: 5000.0,
+                spike_start_secs: 10,
+                spike_secs: 5,
+                duration_secs: 30,
+            },
+        ),
+        (
+            "Soak",
+            LoadProfile::Soak {
+                rps: 500.0,
+                duration_secs: 300,
+            },
+        ),
+    ]
+}
+
+/// Upload payloads and encodings, with the iterations each runs.
+fn uploads() -> Vec<(&'static str, UploadPayload, UploadEncoding, usize)> {
+    vec![
+        ("", UploadPayload::Asset, UploadEncoding::Raw, 500),
+        (
+            "Multipart",
+            UploadPayload::Asset,
+            UploadEncoding::Multipart,
+            500,
+        ),
+        (
+            "64KiB",
+            UploadPayload::Generated {
+                size_bytes: 64 * 1024,
+            },
+            UploadEncoding::Raw,
+            2000,
+        ),
+        (
+            "16MiB",
+            UploadPayload::Generated {
+                size_bytes: 16 * 1024 * 1024,
+            },
+            UploadEncoding::Raw,
+            20,
+        ),
+    ]
+}
+
+/// Whole large file downloads and range requests into the 64 MiB file, with the iterations each
+/// runs.
+fn large_files() -> Vec<(&'static str, LargeFileOptions, usize)> {
+    const MIB: u64 = 1024 * 1024;
+    let range = |range| LargeFileOptions {
+        size_bytes: 64 * MIB,
+        range: Some(range),
+    };
+    vec![
+        (
+            "64MiB",
+            LargeFileOptions {
+                size_bytes: 64 * MIB,
+                range: None,
+            },
+            20,
+        ),
+        (
+            "1GiB",
+            LargeFileOptions {
+                size_bytes: 1024 * MIB,
+                range: None,
+            },
+            3,
+        ),
+        (
+            "RangeSingle",
+            range(ByteRange::Single {
+                start: MIB,
+                length: 64 * 1024,
+            }),
+            2000,
+        ),
+        (
+            "RangeSuffix",
+            range(ByteRange::Suffix { length: 64 * 1024 }),
+            2000,
+        ),
+        (
+            "RangeMulti",
+            range(ByteRange::Multi {
+                count: 4,
+                length: 16 * 1024,
+            }),
+            2000,
+        ),
+    ]
+}
+
+/// Square matrix sizes of the matrix multiplication sweep, with the iterations each runs.
+fn matrix_sizes() -> Vec<(usize, usize)> {
+    vec![(10, 1000), (100, 100), (300, 20)]
+}
+
+/// Connection handling variants run against the plaintext endpoint.
+fn connection_modes() -> Vec<(&'static str, ConnectionMode)> {
+    vec![
+        (
+            "KeepAlive64",
+            ConnectionMode::KeepAlive {
+                max_connections: Some(64),
+            },
+        ),
+        ("ConnectionClose", ConnectionMode::Close),
+        ("ClientPerRequest", ConnectionMode::ClientPerRequest),
+    ]
+}
@@ -0,0 +1,214 @@
+use std::{collections::BTreeMap, fmt, sync::Mutex, time::Duration};
+
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Outcome of a single request, relative to the start of the run.
+#[derive(Debug, Clone)]
+pub struct RequestSample {
+    pub completed: Duration,
+    pub latency: Duration,
+    pub bytes: u64,
+    pub ok: bool,
+}
+
+/// Requests completed within one second of a benchmark run.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeSeriesBucket {
+    pub second: u64,
+    pub rps: usize,
+    pub errors: usize,
+    pub bytes: u64,
+    pub latency_mean_ms: f64,
+    pub latency_p50_ms: f64,
+    pub latency_p99_ms: f64,
+    pub latency_max_ms: f64,
+}
+
+/// Latencies recorded in microseconds with three significant digits. Serialized as
+/// `[value, count]` pairs so that histograms from several load workers can be merged.
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram(Histogram<u64>);
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self(Histogram::new(3).expect("3 significant digits is a valid precision"))
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        self.record_n(latency.as_micros() as u64, 1);
+    }
+
+    fn record_n(&mut self, micros: u64, count: u64) {
+        // auto-resizing histograms grow to fit large values and only reject values below their
+        // lowest trackable value, whereas saturating records would clamp to the current bounds
+        let _ = self.0.record_n(micros.max(1), count);
+    }
+
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for value in other.0.iter_recorded() {
+            self.record_n(value.value_iterated_to(), value.count_at_value());
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.0.len()
+    }
+
+    // the accessors below report 0 for a second without requests
+
+    pub fn mean_ms(&self) -> f64 {
+        if self.0.is_empty() {
+            return 0.0;
+        }
+        self.0.mean() / 1000.0
+    }
+
+    pub fn percentile_ms(&self, percentile: f64) -> f64 {
+        if self.0.is_empty() {
+            return 0.0;
+        }
+        self.0.value_at_percentile(percentile) as f64 / 1000.0
+    }
+
+    pub fn max_ms(&self) -> f64 {
+        if self.0.is_empty() {
+            return 0.0;
+        }
+        self.0.max() as f64 / 1000.0
+    }
+}
+
+impl Serialize for LatencyHistogram {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            self.0
+                .iter_recorded()
+                .map(|value| (value.value_iterated_to(), value.count_at_value())),
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for LatencyHistogram {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut histogram = LatencyHistogram::default();
+        for (micros, count) in Vec::<(u64, u64)>::deserialize(deserializer)? {
+            histogram.record_n(micros, count);
+        }
+        Ok(histogram)
+    }
+}
+
+/// Mergeable aggregate of the requests completed within one second of a run.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SecondHistogram {
+    pub second: u64,
+    pub errors: usize,
+    pub bytes: u64,
+    pub latency: LatencyHistogram,
+}
+
+impl SecondHistogram {
+    fn record(&mut self, sample: &RequestSample) {
+        self.latency.record(sample.latency);
+        self.bytes += sample.bytes;
+        if !sample.ok {
+            self.errors += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &SecondHistogram) {
+        self.errors += other.errors;
+        self.bytes += other.bytes;
+        self.latency.merge(&other.latency);
+    }
+}
+
+/// Groups request samples into one histogram per second of completion time.
+pub fn histogram_samples(samples: &[RequestSample]) -> Vec<SecondHistogram> {
+    let mut histograms = vec![];
+    for sample in samples {
+        second_histogram(&mut histograms, sample.completed.as_secs()).record(sample);
+    }
+    histograms
+}
+
+/// Adds `other` into `histograms`, second by second.
+pub fn merge_histograms(histograms: &mut Vec<SecondHistogram>, other: &[SecondHistogram]) {
+    for histogram in other {
+        second_histogram(histograms, histogram.second).merge(histogram);
+    }
+}
+
+fn second_histogram(histograms: &mut Vec<SecondHistogram>, second: u64) -> &mut SecondHistogram {
+    while histograms.len() <= second as usize {
+        histograms.push(SecondHistogram {
+            second: histograms.len() as u64,
+            ..Default::default()
+        });
+    }
+    &mut histograms[second as usize]
+}
+
+/// Turns per-second histograms into the buckets reported in the results.
+pub fn bucket_histograms(histograms: &[SecondHistogram]) -> Vec<TimeSeriesBucket> {
+    histograms
+        .iter()
+        .map(|histogram| {
+            let latency = &histogram.latency;
+            TimeSeriesBucket {
+                second: histogram.second,
+                rps: latency.len() as usize,
+                errors: histogram.errors,
+                bytes: histogram.bytes,
+                latency_mean_ms: latency.mean_ms(),
+                latency_p50_ms: latency.percentile_ms(50.0),
+                latency_p99_ms: latency.percentile_ms(99.0),
+                latency_max_ms: latency.max_ms(),
+            }
+        })
+        .collect()
+}
+
+/// Per-second histograms filled as requests complete, so that a load worker can stream every
+/// second once it is over instead of waiting for the whole run.
+#[derive(Default)]
+pub struct LiveHistograms(Mutex<BTreeMap<u64, SecondHistogram>>);
+
+impl fmt::Debug for LiveHistograms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LiveHistograms").finish_non_exhaustive()
+    }
+}
+
+impl LiveHistograms {
+    pub fn record(&self, sample: &RequestSample) {
+        let Ok(mut seconds) = self.0.lock() else {
+            return;
+        };
+        let second = sample.completed.as_secs();
+        seconds
+            .entry(second)
+            .or_insert_with(|| SecondHistogram {
+                second,
+                ..Default::default()
+            })
+            .record(sample);
+    }
+
+    /// Removes the histograms of the seconds before `second`. Requests completing into a second
+    /// after it was taken start a new histogram for it, which merges with the first.
+    pub fn take_before(&self, second: u64) -> Vec<SecondHistogram> {
+        let Ok(mut seconds) = self.0.lock() else {
+            return vec![];
+        };
+        let later = seconds.split_off(&second);
+        std::mem::replace(&mut *seconds, later)
+            .into_values()
+            .collect()
+    }
+}
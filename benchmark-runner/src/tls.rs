@@ -0,0 +1,96 @@
+use std::fs;
+
+use log::info;
+use rcgen::{
+    BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair,
+    KeyUsagePurpose,
+};
+use rustls::{
+    ClientConfig, RootCertStore,
+    client::Resumption,
+    pki_types::{CertificateDer, pem::PemObject},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::protocol::Protocol;
+
+const TLS_DIR: &str = "/assets/tls";
+const CA_CERT_PATH: &str = "/assets/tls/ca.crt";
+const SERVER_CERT_PATH: &str = "/assets/tls/server.crt";
+const SERVER_KEY_PATH: &str = "/assets/tls/server.key";
+
+#[derive(Error, Debug)]
+pub enum TlsError {
+    #[error("IO: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Certificate generation: {0}")]
+    Rcgen(#[from] rcgen::Error),
+
+    #[error("Rustls: {0}")]
+    Rustls(#[from] rustls::Error),
+
+    #[error("PEM: {0}")]
+    Pem(#[from] rustls::pki_types::pem::Error),
+}
+
+/// TLS settings for a benchmark run.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsOptions {
+    /// Resume sessions from earlier handshakes instead of running a full handshake each time.
+    pub session_resumption: bool,
+}
+
+/// Writes a fresh CA and a `web` server certificate signed by it into the assets volume, where
+/// webservers that opt into TLS pick them up.
+pub fn generate_certificates() -> Result<(), TlsError> {
+    info!("generating TLS certificates in {TLS_DIR}");
+    fs::create_dir_all(TLS_DIR)?;
+
+    let mut ca_params = CertificateParams::new(Vec::<String>::new())?;
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    ca_params
+        .distinguished_name
+        .push(DnType::CommonName, "webserver-benchmarks CA");
+    ca_params.key_usages = vec![
+        KeyUsagePurpose::KeyCertSign,
+        KeyUsagePurpose::CrlSign,
+        KeyUsagePurpose::DigitalSignature,
+    ];
+    let ca_key = KeyPair::generate()?;
+    let ca_cert = ca_params.self_signed(&ca_key)?;
+
+    let mut server_params = CertificateParams::new(vec!["web".to_string()])?;
+    server_params
+        .distinguished_name
+        .push(DnType::CommonName, "web");
+    server_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+    let server_key = KeyPair::generate()?;
+    let server_cert = server_params.signed_by(&server_key, &ca_cert, &ca_key)?;
+
+    fs::write(CA_CERT_PATH, ca_cert.pem())?;
+    fs::write(SERVER_CERT_PATH, server_cert.pem())?;
+    fs::write(SERVER_KEY_PATH, server_key.serialize_pem())?;
+    Ok(())
+}
+
+/// Client configuration trusting the generated CA and offering only the ALPN protocol
+/// matching `protocol`.
+pub fn client_config(tls: &TlsOptions, protocol: Protocol) -> Result<ClientConfig, TlsError> {
+    let mut roots = RootCertStore::empty();
+    roots.add(CertificateDer::from_pem_file(CA_CERT_PATH)?)?;
+
+    let mut config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    config.alpn_protocols = match protocol {
+        Protocol::Http1 => vec![b"http/1.1".to_vec()],
+        Protocol::Http2 { .. } => vec![b"h2".to_vec()],
+    };
+    if !tls.session_resumption {
+        config.resumption = Resumption::disabled();
+    }
+    Ok(config)
+}
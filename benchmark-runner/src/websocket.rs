@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use thiserror::Error;
+use tokio::{net::TcpStream, time::timeout};
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream, connect_async_with_config,
+    tungstenite::{self, Message},
+};
+
+use crate::RunOptions;
+
+/// Longest wait for a message before the webserver is considered stuck.
+const MESSAGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Error, Debug)]
+pub enum WebSocketError {
+    #[error("WebSocket: {0}")]
+    Tungstenite(#[from] tungstenite::Error),
+
+    #[error("Handshake rejected with status code {0}")]
+    Rejected(u16),
+
+    #[error("Connection closed with messages outstanding")]
+    Closed,
+
+    #[error("Timeout waiting for a message")]
+    Timeout,
+
+    #[error("Unsupported: {0}")]
+    Unsupported(String),
+}
+
+pub type WebSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Opens a WebSocket to `path` on the webserver, or the null server when calibrating.
+pub async fn connect(options: &RunOptions, path: &str) -> Result<WebSocket, WebSocketError> {
+    if options.tls.is_some() {
+        return Err(WebSocketError::Unsupported(
+            "WebSockets over TLS".to_string(),
+        ));
+    }
+    let url = options.url(path).replacen("http://", "ws://", 1);
+    match connect_async_with_config(url, None, true).await {
+        Ok((socket, _)) => Ok(socket),
+        Err(tungstenite::Error::Http(response)) => {
+            Err(WebSocketError::Rejected(response.status().as_u16()))
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Sends `payload` as a binary message and flushes it.
+pub async fn send<S>(sink: &mut S, payload: Bytes) -> Result<(), WebSocketError>
+where
+    S: Sink<Message, Error = tungstenite::Error> + Unpin,
+{
+    sink.send(Message::Binary(payload)).await?;
+    Ok(())
+}
+
+/// Payload of the next text or binary message, skipping control frames, which tungstenite answers
+/// on its own.
+pub async fn receive<S>(stream: &mut S) -> Result<Bytes, WebSocketError>
+where
+    S: Stream<Item = Result<Message, tungstenite::Error>> + Unpin,
+{
+    loop {
+        let message = timeout(MESSAGE_TIMEOUT, stream.next())
+            .await
+            .map_err(|_| WebSocketError::Timeout)?;
+        match message.ok_or(WebSocketError::Closed)?? {
+            Message::Binary(payload) => return Ok(payload),
+            Message::Text(text) => return Ok(Bytes::from(text)),
+            Message::Close(_) => return Err(WebSocketError::Closed),
+            Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => {}
+        }
+    }
+}
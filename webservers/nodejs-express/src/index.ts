@@ -1,7 +1,10 @@
+import fs from "node:fs";
+import https from "node:https";
 import express from "express";
 
 const app = express();
 const port = 8000;
+const tlsPort = 8443;
 
 app.get("/benchmark/health", (_req, res) => {
 	res.send({});
@@ -19,3 +22,11 @@ app.get("/benchmark/download-binary", (_req, res) => {
 app.listen(port, () => {
 	console.log(`app listening on port ${port}`);
 });
+
+const tlsOptions = {
+	cert: fs.readFileSync("/assets/tls/server.crt"),
+	key: fs.readFileSync("/assets/tls/server.key"),
+};
+https.createServer(tlsOptions, app).listen(tlsPort, () => {
+	console.log(`app listening on port ${tlsPort} (tls)`);
+});